@@ -0,0 +1,288 @@
+//! Aggregations over [Record]s, such as the total gold across every character
+//! or the number of enemies of each model.
+//!
+//! Aggregates are computed over the records of a [Select], optionally split
+//! into groups with [Select::group_by]:
+//!
+//! ```rust
+//! use gddb::{Aggregate, Database, Record, Value};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("GAME", None, false);
+//!
+//!     for (model, gold) in [("Knight", 10), ("Knight", 20), ("Mage", 5)] {
+//!         let mut character = Record::new(model.into());
+//!         character.set("gold", gold);
+//!         db.create(character).unwrap();
+//!     }
+//!
+//!     let total = db.select().aggregate(&Aggregate::parse("sum(gold)").unwrap());
+//!     assert_eq!(total.unwrap(), Value::Int(35));
+//!
+//!     let groups = db.select().group_by("model");
+//!     assert_eq!(groups[0].0, Value::from("Knight"));
+//!     assert_eq!(Aggregate::Count.apply(&groups[0].1).unwrap(), Value::Int(2));
+//! }
+//! ```
+
+use crate::prelude::*;
+use crate::query::resolve;
+
+/// A function computed over a set of records, see the
+/// [module docs](self).
+///
+/// Paths are resolved the same way as paths in a [Query], and records where
+/// the path is missing or `null` are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Aggregate {
+    /// The number of records.
+    Count,
+
+    /// The sum of the numbers at a path, which is an integer if they all are
+    /// and it fits in one.
+    Sum(String),
+
+    /// The average of the numbers at a path, or `null` if there are none.
+    Avg(String),
+
+    /// The smallest value at a path, or `null` if there are none. Values are
+    /// compared with [Value::total_cmp].
+    Min(String),
+
+    /// The largest value at a path, see [Aggregate::Min].
+    Max(String),
+}
+
+impl Aggregate {
+    /// Parses an aggregate such as `count`, `sum(gold)` or `avg(stats.hp)`.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::BadQuery] if the aggregate is not valid.
+    pub fn parse(aggregate: &str) -> Result<Self, DatabaseError> {
+        let aggregate = aggregate.trim();
+        let invalid = || DatabaseError::BadQuery(format!("invalid aggregate {:?}", aggregate));
+
+        if aggregate.eq_ignore_ascii_case("count") {
+            return Ok(Aggregate::Count);
+        }
+
+        let (function, path) = aggregate
+            .strip_suffix(')')
+            .and_then(|aggregate| aggregate.split_once('('))
+            .ok_or_else(invalid)?;
+        let path = path.trim().to_string();
+
+        if path.is_empty() {
+            return Err(invalid());
+        }
+
+        match function.trim().to_ascii_lowercase().as_str() {
+            "count" => Ok(Aggregate::Count),
+            "sum" => Ok(Aggregate::Sum(path)),
+            "avg" => Ok(Aggregate::Avg(path)),
+            "min" => Ok(Aggregate::Min(path)),
+            "max" => Ok(Aggregate::Max(path)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Computes the aggregate over a set of records.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::WrongType] with the path if [Aggregate::Sum]
+    /// or [Aggregate::Avg] finds a value that isn't a number.
+    pub fn apply(&self, records: &[&Record]) -> Result<Value, DatabaseError> {
+        let path = match self {
+            Aggregate::Count => return Ok(Value::Int(records.len() as i64)),
+            Aggregate::Sum(path)
+            | Aggregate::Avg(path)
+            | Aggregate::Min(path)
+            | Aggregate::Max(path) => path,
+        };
+
+        let values = records
+            .iter()
+            .filter_map(|record| resolve(record, path))
+            .filter(|value| !value.is_null());
+
+        let wrong_type = || DatabaseError::WrongType(path.clone());
+
+        Ok(match self {
+            Aggregate::Count => unreachable!(),
+            Aggregate::Sum(_) => {
+                let mut sum = Value::Int(0);
+
+                for value in values {
+                    sum = match (sum.as_i64(), value.as_i64()) {
+                        (Some(total), Some(i)) => total
+                            .checked_add(i)
+                            .map_or(Value::Float(total as f64 + i as f64), Value::Int),
+                        _ => {
+                            let f = value.as_f64().ok_or_else(wrong_type)?;
+                            Value::Float(sum.as_f64().unwrap_or_default() + f)
+                        }
+                    };
+                }
+
+                sum
+            }
+            Aggregate::Avg(_) => {
+                let numbers = values
+                    .map(|value| value.as_f64().ok_or_else(wrong_type))
+                    .collect::<Result<Vec<f64>, DatabaseError>>()?;
+
+                match numbers.len() {
+                    0 => Value::Null,
+                    len => Value::Float(numbers.iter().sum::<f64>() / len as f64),
+                }
+            }
+            Aggregate::Min(_) => values
+                .min_by(|a, b| a.total_cmp(b))
+                .map_or(Value::Null, |value| value.into_owned()),
+            Aggregate::Max(_) => values
+                .max_by(|a, b| a.total_cmp(b))
+                .map_or(Value::Null, |value| value.into_owned()),
+        })
+    }
+}
+
+impl<'a> Select<'a> {
+    /// Computes an aggregate over the selected records, see
+    /// [Aggregate::apply].
+    pub fn aggregate(&self, aggregate: &Aggregate) -> Result<Value, DatabaseError> {
+        aggregate.apply(&self.records())
+    }
+
+    /// Splits the selected records into groups by the value at a path,
+    /// ordered by [Value::total_cmp]. Missing values are grouped with `null`,
+    /// which is always first.
+    pub fn group_by(&self, path: &str) -> Vec<(Value, Vec<&'a Record>)> {
+        let mut values: Vec<(Value, &'a Record)> = self
+            .records()
+            .into_iter()
+            .map(|record| {
+                let value = resolve(record, path).map_or(Value::Null, |value| value.into_owned());
+                (value, record)
+            })
+            .collect();
+        values.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        let mut groups: Vec<(Value, Vec<&'a Record>)> = Vec::new();
+
+        for (value, record) in values {
+            match groups.last_mut() {
+                Some((group, records)) if group.total_cmp(&value).is_eq() => records.push(record),
+                _ => groups.push((value, vec![record])),
+            }
+        }
+
+        groups
+    }
+}
+
+impl Database<Record> {
+    /// Computes an aggregate over every record, see [Select::aggregate].
+    pub fn aggregate(&self, aggregate: &Aggregate) -> Result<Value, DatabaseError> {
+        self.select().aggregate(aggregate)
+    }
+
+    /// Splits every record into groups by the value at a path, see
+    /// [Select::group_by].
+    pub fn group_by(&self, path: &str) -> Vec<(Value, Vec<&Record>)> {
+        self.select().group_by(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests each aggregate, including over missing and mixed values
+    #[test]
+    fn aggregates() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Aggregate test", None, false);
+
+        for (model, gold) in [("Knight", Value::Int(10)), ("Knight", Value::Float(2.5))] {
+            let mut character = Record::new(model.into());
+            character.set("gold", gold);
+            db.create(character)?;
+        }
+
+        let mut mage = Record::new("Mage".into());
+        mage.set("gold", 5);
+        mage.set("name", "Zed");
+        db.create(mage)?;
+        db.create(Record::new("Mage".into()))?;
+
+        let select = db.select();
+        let aggregate = |spec: &str| select.aggregate(&Aggregate::parse(spec)?);
+
+        assert_eq!(aggregate("count")?, Value::Int(4));
+        assert_eq!(aggregate("sum(gold)")?, Value::Float(17.5));
+        assert_eq!(aggregate("avg(attributes.gold)")?, Value::Float(17.5 / 3.0));
+        assert_eq!(aggregate("min(gold)")?, Value::Float(2.5));
+        assert_eq!(aggregate("MAX(gold)")?, Value::Int(10));
+        assert_eq!(aggregate("max(name)")?, Value::from("Zed"));
+        assert_eq!(aggregate("avg(missing)")?, Value::Null);
+        assert!(aggregate("median(gold)").is_err());
+        assert!(aggregate("sum()").is_err());
+        assert!(matches!(
+            aggregate("sum(name)"),
+            Err(DatabaseError::WrongType(path)) if path == "name"
+        ));
+        assert!(matches!(
+            aggregate("avg(name)"),
+            Err(DatabaseError::WrongType(_))
+        ));
+
+        let groups = db
+            .select()
+            .filter(Query::parse("exists gold")?)
+            .group_by("model");
+        let sums: Vec<(&Value, Value)> = groups
+            .iter()
+            .map(|(model, records)| (model, Aggregate::Sum("gold".into()).apply(records)))
+            .map(|(model, sum)| sum.map(|sum| (model, sum)))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            sums,
+            vec![
+                (&Value::from("Knight"), Value::Float(12.5)),
+                (&Value::from("Mage"), Value::Int(5))
+            ]
+        );
+
+        let mut null_name = Record::new("Mage".into());
+        null_name.set("name", "null");
+        db.create(null_name)?;
+
+        let by_name = db.select().group_by("name");
+        assert_eq!(by_name.len(), 3);
+        assert_eq!((&by_name[0].0, by_name[0].1.len()), (&Value::Null, 3));
+        assert_eq!(
+            (&by_name[2].0, by_name[2].1.len()),
+            (&Value::from("null"), 1)
+        );
+
+        Ok(())
+    }
+
+    /// Tests sums of integers switch to a float rather than overflowing
+    #[test]
+    fn sum_overflow() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Aggregate test", None, false);
+
+        for gold in [i64::MAX, 1] {
+            let mut character = Record::new("Knight".into());
+            character.set("gold", gold);
+            db.create(character)?;
+        }
+
+        let sum = db.aggregate(&Aggregate::Sum("gold".into()))?;
+        assert_eq!(sum, Value::Float(i64::MAX as f64 + 1.0));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+//! Atomic operations on [Record] attributes, for values like gold, XP and
+//! counters that are changed from many places.
+//!
+//! Each operation reads and writes the attribute in a single change made with
+//! [Database::modify], so indexes, the journal, the history and observers see
+//! it like any other update, and returns the new value of the attribute.
+//! Paths are set with [Value::set_path], so a path through something that
+//! isn't a map or array, or past the end of an array, fails with
+//! [DatabaseError::WrongType] rather than replacing it.
+//!
+//! ```rust
+//! use gddb::{Database, Record, Value};
+//!
+//! fn main() {
+//!     let player = Record::new("Player".into());
+//!     let mut db = Database::new("GAME", None, false);
+//!     db.create(player.clone()).unwrap();
+//!
+//!     db.increment(&player.uuid, "gold", 50).unwrap();
+//!     let gold = db.decrement(&player.uuid, "gold", 20).unwrap();
+//!     assert_eq!(gold, Value::Int(30));
+//!
+//!     let quests = db.append_to_array(&player.uuid, "quests", "rescue").unwrap();
+//!     assert_eq!(quests, Value::from(vec!["rescue"]));
+//! }
+//! ```
+
+use crate::prelude::*;
+use std::cmp::Ordering;
+
+impl Database<Record> {
+    /// Adds a number to an attribute, where a missing or `null` attribute
+    /// counts as `0`. Adding two integers gives an integer, and anything else
+    /// a float.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ItemNotFound] if there's no record with the
+    /// uuid, or [DatabaseError::WrongType] if the attribute or amount isn't a
+    /// number.
+    pub fn increment(
+        &mut self,
+        uuid: &str,
+        path: &str,
+        amount: impl Into<Value>,
+    ) -> Result<Value, DatabaseError> {
+        let amount = amount.into();
+
+        self.apply_to_attribute(uuid, path, |value| {
+            add(value.unwrap_or(&Value::Int(0)), &amount)
+        })
+    }
+
+    /// Subtracts a number from an attribute, see [Database::increment].
+    pub fn decrement(
+        &mut self,
+        uuid: &str,
+        path: &str,
+        amount: impl Into<Value>,
+    ) -> Result<Value, DatabaseError> {
+        let amount = match amount.into() {
+            Value::Int(i) => Value::Int(i.saturating_neg()),
+            Value::Float(f) => Value::Float(-f),
+            _ => return Err(DatabaseError::WrongType(path.into())),
+        };
+
+        self.increment(uuid, path, amount)
+    }
+
+    /// Sets a numeric attribute to `value` if it is smaller, so the attribute
+    /// never goes above it. A missing or `null` attribute is set to `value`.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ItemNotFound] if there's no record with the
+    /// uuid, or [DatabaseError::WrongType] if the attribute or value isn't a
+    /// number.
+    pub fn min(
+        &mut self,
+        uuid: &str,
+        path: &str,
+        value: impl Into<Value>,
+    ) -> Result<Value, DatabaseError> {
+        let value = value.into();
+
+        self.apply_to_attribute(uuid, path, |current| {
+            keep_if(current, value, Ordering::Less)
+        })
+    }
+
+    /// Sets a numeric attribute to `value` if it is larger, so the attribute
+    /// never goes below it, see [Database::min].
+    pub fn max(
+        &mut self,
+        uuid: &str,
+        path: &str,
+        value: impl Into<Value>,
+    ) -> Result<Value, DatabaseError> {
+        let value = value.into();
+
+        self.apply_to_attribute(uuid, path, |current| {
+            keep_if(current, value, Ordering::Greater)
+        })
+    }
+
+    /// Appends a value to an array attribute, where a missing or `null`
+    /// attribute counts as an empty array.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ItemNotFound] if there's no record with the
+    /// uuid, or [DatabaseError::WrongType] if the attribute isn't an array.
+    pub fn append_to_array(
+        &mut self,
+        uuid: &str,
+        path: &str,
+        value: impl Into<Value>,
+    ) -> Result<Value, DatabaseError> {
+        let value = value.into();
+
+        self.apply_to_attribute(uuid, path, |current| {
+            let mut items = array(current)?;
+            items.push(value);
+
+            Some(Value::Array(items))
+        })
+    }
+
+    /// Removes every element equal to `value` from an array attribute, see
+    /// [Database::append_to_array].
+    pub fn remove_from_array(
+        &mut self,
+        uuid: &str,
+        path: &str,
+        value: impl Into<Value>,
+    ) -> Result<Value, DatabaseError> {
+        let value = value.into();
+
+        self.apply_to_attribute(uuid, path, |current| {
+            let mut items = array(current)?;
+            items.retain(|item| item != &value);
+
+            Some(Value::Array(items))
+        })
+    }
+
+    /// Replaces an attribute with the value returned by `f`, given the current
+    /// value if there is one, returning the new value. Returning
+    /// [Option::None] leaves the record unchanged and reports
+    /// [DatabaseError::WrongType].
+    fn apply_to_attribute(
+        &mut self,
+        uuid: &str,
+        path: &str,
+        f: impl FnOnce(Option<&Value>) -> Option<Value>,
+    ) -> Result<Value, DatabaseError> {
+        self.modify(uuid, |record| {
+            let current = record.get_path(path).filter(|value| !value.is_null());
+            let value = f(current).ok_or_else(|| DatabaseError::WrongType(path.into()))?;
+
+            record.attributes.set_path(path, value.clone())?;
+            Ok(value)
+        })?
+    }
+}
+
+/// Adds two numbers, keeping integers as integers where possible.
+fn add(a: &Value, b: &Value) -> Option<Value> {
+    match (a, b) {
+        (Value::Int(a), Value::Int(b)) => Some(Value::Int(a.saturating_add(*b))),
+        _ => Some(Value::Float(a.as_f64()? + b.as_f64()?)),
+    }
+}
+
+/// Returns `value` if it compares to `current` as `ordering`, or `current`
+/// otherwise. Both must be numbers.
+fn keep_if(current: Option<&Value>, value: Value, ordering: Ordering) -> Option<Value> {
+    let number = value.as_f64()?;
+
+    match current {
+        None => Some(value),
+        Some(current) => match number.partial_cmp(&current.as_f64()?)? {
+            order if order == ordering => Some(value),
+            _ => Some(current.clone()),
+        },
+    }
+}
+
+/// Copies the items of an array attribute, where a missing attribute is an
+/// empty array.
+fn array(current: Option<&Value>) -> Option<Vec<Value>> {
+    match current {
+        None => Some(Vec::new()),
+        Some(value) => value.as_array().cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests numeric operations keep their type and leave records unchanged
+    /// when given the wrong type
+    #[test]
+    fn numeric_operations() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Atomic test", None, false);
+        let mut player = Record::new("Player".into());
+        player.set("name", "Hero");
+        db.create(player.clone())?;
+
+        assert_eq!(db.increment(&player.uuid, "gold", 10)?, Value::Int(10));
+        assert_eq!(db.decrement(&player.uuid, "gold", 15)?, Value::Int(-5));
+        assert_eq!(db.increment(&player.uuid, "gold", 0.5)?, Value::Float(-4.5));
+        assert_eq!(db.increment(&player.uuid, "stats.xp", 3)?, Value::Int(3));
+
+        assert_eq!(db.min(&player.uuid, "stats.xp", 1)?, Value::Int(1));
+        assert_eq!(db.min(&player.uuid, "stats.xp", 2)?, Value::Int(1));
+        assert_eq!(db.max(&player.uuid, "stats.xp", 5)?, Value::Int(5));
+        assert_eq!(db.max(&player.uuid, "level", 1)?, Value::Int(1));
+
+        let before = db.get(&player.uuid)?.clone();
+        assert!(matches!(
+            db.increment(&player.uuid, "name", 1),
+            Err(DatabaseError::WrongType(_))
+        ));
+        assert!(matches!(
+            db.max(&player.uuid, "gold", "lots"),
+            Err(DatabaseError::WrongType(_))
+        ));
+        assert!(matches!(
+            db.increment("missing", "gold", 1),
+            Err(DatabaseError::ItemNotFound)
+        ));
+        assert!(matches!(
+            db.increment(&player.uuid, "name.first", 1),
+            Err(DatabaseError::WrongType(_))
+        ));
+        assert_eq!(db.get(&player.uuid)?, &before);
+
+        Ok(())
+    }
+
+    /// Tests appending to and removing from array attributes
+    #[test]
+    fn array_operations() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Atomic test", None, false);
+        let player = Record::new("Player".into());
+        db.create(player.clone())?;
+
+        db.append_to_array(&player.uuid, "inventory", "sword")?;
+        db.append_to_array(&player.uuid, "inventory", "potion")?;
+        assert_eq!(
+            db.append_to_array(&player.uuid, "inventory", "potion")?,
+            Value::from(vec!["sword", "potion", "potion"])
+        );
+
+        assert_eq!(
+            db.remove_from_array(&player.uuid, "inventory", "potion")?,
+            Value::from(vec!["sword"])
+        );
+        assert!(matches!(
+            db.append_to_array(&player.uuid, "inventory.0", "shield"),
+            Err(DatabaseError::WrongType(_))
+        ));
+
+        db.append_to_array(&player.uuid, "inventory", 1)?;
+        assert_eq!(db.increment(&player.uuid, "inventory.1", 1)?, Value::Int(2));
+        assert_eq!(db.increment(&player.uuid, "inventory.2", 1)?, Value::Int(1));
+        assert!(matches!(
+            db.increment(&player.uuid, "inventory.5", 1),
+            Err(DatabaseError::WrongType(_))
+        ));
+        assert_eq!(
+            db.get(&player.uuid)?.get("inventory"),
+            Some(&Value::from(vec![
+                Value::from("sword"),
+                Value::Int(2),
+                Value::Int(1)
+            ]))
+        );
+
+        Ok(())
+    }
+}
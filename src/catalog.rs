@@ -0,0 +1,370 @@
+//! Catalogs of named collections, keeping different kinds of data such as
+//! players, items and quests in separate [Database]s.
+//!
+//! Each collection is a full [Database] with its own indexes,
+//! [Database::strict_dupes] policy and other settings. A catalog can be saved
+//! to a single file with [Catalog::dump_file], or to a directory holding a
+//! file for each collection with [Catalog::dump_dir].
+//!
+//! ```rust
+//! use gddb::{Catalog, Record};
+//!
+//! fn main() {
+//!     let mut catalog: Catalog<Record> = Catalog::new();
+//!
+//!     catalog.create_collection("players", true).unwrap();
+//!     catalog.create_collection("items", false).unwrap();
+//!
+//!     let players = catalog.collection_mut("players").unwrap();
+//!     players.create(Record::new("Player".into())).unwrap();
+//!
+//!     assert_eq!(catalog.collection("players").unwrap().len(), 1);
+//!     assert_eq!(catalog.names().collect::<Vec<_>>(), vec!["items", "players"]);
+//! }
+//! ```
+
+use crate::database::{get_stream_from_path, write_atomic};
+use crate::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Named collections of items, see the [module docs](self).
+#[derive(Clone)]
+pub struct Catalog<T: Keyed> {
+    collections: BTreeMap<String, Database<T>>,
+}
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Catalog<Record> {
+    /// Creates an empty catalog.
+    pub fn new() -> Self {
+        Self {
+            collections: BTreeMap::new(),
+        }
+    }
+
+    /// Creates an empty collection, labelled with its name.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::DupeFound] if a collection with the name
+    /// already exists, or [DatabaseError::BadQuery] if the name can't be used
+    /// as a file name by [Catalog::dump_dir].
+    pub fn create_collection(
+        &mut self,
+        name: impl Into<String>,
+        strict_dupes: bool,
+    ) -> Result<&mut Database<Record>, DatabaseError> {
+        let name = name.into();
+        check_name(&name)?;
+
+        if self.collections.contains_key(&name) {
+            return Err(DatabaseError::DupeFound);
+        }
+
+        let collection = Database::new(name.clone(), None, strict_dupes);
+        Ok(self.collections.entry(name).or_insert(collection))
+    }
+
+    /// Returns a collection, creating an empty one with the given
+    /// [Database::strict_dupes] policy if it doesn't exist yet.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::BadQuery] if the name isn't valid, see
+    /// [Catalog::create_collection].
+    pub fn collection_or_create(
+        &mut self,
+        name: impl Into<String>,
+        strict_dupes: bool,
+    ) -> Result<&mut Database<Record>, DatabaseError> {
+        let name = name.into();
+        check_name(&name)?;
+
+        Ok(self
+            .collections
+            .entry(name.clone())
+            .or_insert_with(|| Database::new(name, None, strict_dupes)))
+    }
+
+    /// Returns a collection by its name.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::CollectionNotFound] if there's no
+    /// collection with the name.
+    pub fn collection(&self, name: &str) -> Result<&Database<Record>, DatabaseError> {
+        self.collections
+            .get(name)
+            .ok_or_else(|| DatabaseError::CollectionNotFound(name.into()))
+    }
+
+    /// Returns a collection by its name for changing, see
+    /// [Catalog::collection].
+    pub fn collection_mut(&mut self, name: &str) -> Result<&mut Database<Record>, DatabaseError> {
+        self.collections
+            .get_mut(name)
+            .ok_or_else(|| DatabaseError::CollectionNotFound(name.into()))
+    }
+
+    /// Removes a collection from the catalog, returning it.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::CollectionNotFound] if there's no
+    /// collection with the name.
+    pub fn drop_collection(&mut self, name: &str) -> Result<Database<Record>, DatabaseError> {
+        self.collections
+            .remove(name)
+            .ok_or_else(|| DatabaseError::CollectionNotFound(name.into()))
+    }
+
+    /// The names of every collection, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.collections.keys().map(String::as_str)
+    }
+
+    /// Saves every collection to a single file, in the [Format] inferred from
+    /// its extension. The file is replaced atomically, like
+    /// [Database::dump_db].
+    ///
+    /// Collections keep their settings and items, but like [Database::from]
+    /// only the indexes from [Keyed::indexes] are kept when loading.
+    pub fn dump_file(&self, path: impl Into<PathBuf>) -> Result<(), DatabaseError> {
+        let path = path.into();
+        let mut collections = BTreeMap::new();
+
+        for (name, collection) in self.collections.iter() {
+            collections.insert(name.clone(), collection.to_value()?);
+        }
+
+        let stream = Format::from_path(&path).encode(
+            Database::<Record>::schema_version(),
+            &Self::type_name(),
+            Value::Map(collections),
+        )?;
+
+        write_atomic(&path, &stream, 0)
+    }
+
+    /// Loads a catalog saved with [Catalog::dump_file], migrating the items
+    /// of each collection like [Database::from].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::DatabaseNotFound] if the file doesn't
+    /// exist, [DatabaseError::TypeMismatch] if it isn't a catalog of this
+    /// type, or any error [Database::from] can return.
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, DatabaseError> {
+        let path = path.into();
+        let (header, value) = Format::from_path(&path).decode(&get_stream_from_path(path)?)?;
+
+        if header.type_name != Self::type_name() {
+            return Err(DatabaseError::TypeMismatch(header.type_name));
+        }
+
+        let collections = match value {
+            Value::Map(collections) => collections,
+            _ => return Err(DatabaseError::CorruptFile),
+        };
+
+        let mut catalog = Self::new();
+
+        for (name, collection) in collections {
+            check_name(&name)?;
+            let collection = Database::from_value(header.schema_version, collection)?;
+            catalog.collections.insert(name, collection);
+        }
+
+        Ok(catalog)
+    }
+
+    /// Saves each collection to its own file inside of a directory, named
+    /// after the collection and in its [Database::format] or
+    /// [Format::Bincode], creating the directory if needed.
+    ///
+    /// Files of collections that have since been dropped are left in place.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::BadQuery] without saving anything if a
+    /// name isn't valid, see [Catalog::create_collection].
+    pub fn dump_dir(&self, dir: impl AsRef<Path>) -> Result<(), DatabaseError> {
+        let dir = dir.as_ref();
+
+        for name in self.collections.keys() {
+            check_name(name)?;
+        }
+
+        std::fs::create_dir_all(dir)?;
+
+        for (name, collection) in self.collections.iter() {
+            let format = collection.format.unwrap_or_default();
+            let path = dir.join(format!("{}.{}", name, format.extension()));
+
+            collection.export_db(path, format)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads every collection saved with [Catalog::dump_dir] from a
+    /// directory, ignoring backups and other files. Files with a save's
+    /// extension that aren't a save of this type, such as a `models.json`
+    /// for [Database::load_models], are skipped too. Each collection keeps
+    /// saving in the [Format] it was loaded from.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::DatabaseNotFound] if the directory doesn't
+    /// exist, [DatabaseError::CorruptCollection] if a save in it is damaged,
+    /// or any other error [Database::from] can return.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        let dir = dir.as_ref();
+
+        if !dir.is_dir() {
+            return Err(DatabaseError::DatabaseNotFound);
+        }
+
+        let mut catalog = Self::new();
+
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let format = Format::from_path(&path);
+
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) if path.extension() == Some(format.extension().as_ref()) => name,
+                _ => continue,
+            };
+
+            if !path.is_file() || !format.is_save(&std::fs::read(&path)?) {
+                continue;
+            }
+
+            let mut collection = match Database::from(path.clone()) {
+                Ok(collection) => collection,
+                Err(DatabaseError::TypeMismatch(_)) => continue,
+                Err(DatabaseError::CorruptFile) => {
+                    let file = path.file_name().unwrap_or_default().to_string_lossy();
+                    return Err(DatabaseError::CorruptCollection(file.into_owned()));
+                }
+                Err(error) => return Err(error),
+            };
+            collection.label = name.to_string();
+            collection.format = Some(format);
+
+            catalog.collections.insert(name.to_string(), collection);
+        }
+
+        Ok(catalog)
+    }
+
+    /// The type stored in the header of files saved by [Catalog::dump_file].
+    fn type_name() -> String {
+        format!("Catalog<{}>", Record::type_name())
+    }
+}
+
+/// Checks a collection name can be used as a file name inside of the
+/// directory given to [Catalog::dump_dir], without escaping it.
+fn check_name(name: &str) -> Result<(), DatabaseError> {
+    let invalid =
+        name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']);
+
+    if invalid {
+        return Err(DatabaseError::BadQuery(format!(
+            "invalid collection name {:?}",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Default for Catalog<Record> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Keyed> std::fmt::Debug for Catalog<T>
+where
+    Database<T>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Catalog")
+            .field("collections", &self.collections)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests collections keep their own items and settings, in memory and
+    /// through both ways of saving
+    #[test]
+    fn collections() -> Result<(), DatabaseError> {
+        let mut catalog: Catalog<Record> = Catalog::new();
+        let player = Record::new("Player".into());
+        let sword = Record::new("Item".into());
+
+        catalog
+            .create_collection("players", true)?
+            .create(player.clone())?;
+        catalog
+            .create_collection("items", false)?
+            .create(sword.clone())?;
+        catalog.collection_mut("items")?.format = Some(Format::Json);
+
+        assert!(matches!(
+            catalog.create_collection("players", false),
+            Err(DatabaseError::DupeFound)
+        ));
+        assert!(matches!(
+            catalog.collection("quests"),
+            Err(DatabaseError::CollectionNotFound(_))
+        ));
+        assert!(catalog.collection("items")?.get(&player.uuid).is_err());
+        assert_eq!(catalog.collection_or_create("quests", false)?.len(), 0);
+
+        for name in ["", "..", "../players", "a/b", "a\\b"] {
+            assert!(matches!(
+                catalog.create_collection(name, false),
+                Err(DatabaseError::BadQuery(_))
+            ));
+            assert!(catalog.collection_or_create(name, false).is_err());
+        }
+
+        catalog.drop_collection("quests")?;
+
+        catalog.dump_file("catalog.gddb")?;
+        let loaded: Catalog<Record> = Catalog::from_file("catalog.gddb")?;
+        assert_eq!(loaded.collection("players")?.get(&player.uuid)?, &player);
+        assert!(loaded.collection("players")?.strict_dupes);
+        assert!(matches!(
+            Database::<Record>::from("catalog.gddb"),
+            Err(DatabaseError::TypeMismatch(_))
+        ));
+        std::fs::remove_file("catalog.gddb")?;
+
+        catalog.dump_dir("catalog_dir")?;
+        catalog.dump_dir("catalog_dir")?;
+        std::fs::write("catalog_dir/models.json", r#"{"Player": {}}"#)?;
+        std::fs::write("catalog_dir/notes.gddb", "not a save")?;
+        let loaded: Catalog<Record> = Catalog::from_dir("catalog_dir")?;
+        assert_eq!(loaded.names().collect::<Vec<_>>(), vec!["items", "players"]);
+        assert_eq!(loaded.collection("items")?.get(&sword.uuid)?, &sword);
+        assert_eq!(loaded.collection("items")?.format, Some(Format::Json));
+
+        let players = std::fs::read("catalog_dir/players.gddb")?;
+        std::fs::write("catalog_dir/players.gddb", &players[..players.len() - 1])?;
+        assert!(matches!(
+            Catalog::<Record>::from_dir("catalog_dir"),
+            Err(DatabaseError::CorruptCollection(file)) if file == "players.gddb"
+        ));
+        std::fs::remove_dir_all("catalog_dir")?;
+
+        Ok(())
+    }
+}
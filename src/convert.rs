@@ -0,0 +1,806 @@
+//! Converts any serializable type to and from its [Value] form, the shape it
+//! would have as JSON.
+//!
+//! Unlike a round trip through JSON, floats are kept exactly as they are, so
+//! `NaN` and infinities survive being saved in a binary format or journaled.
+
+use crate::prelude::*;
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, EnumAccess, IntoDeserializer, VariantAccess, Visitor};
+use serde::ser::{self, Impossible};
+use std::collections::BTreeMap;
+use std::fmt;
+
+impl Value {
+    /// Converts an item into its [Value] form, as saved inside of a file.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::IOError] if the item can't be represented,
+    /// e.g. a map with keys that aren't strings or numbers.
+    pub fn serialize_from<T: Serialize + ?Sized>(item: &T) -> Result<Value, DatabaseError> {
+        Ok(item.serialize(ValueSerializer)?)
+    }
+
+    /// Converts the value back into an item, the reverse of
+    /// [Value::serialize_from].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::IOError] if the value doesn't have the
+    /// shape of the item.
+    pub fn deserialize_into<T: DeserializeOwned>(self) -> Result<T, DatabaseError> {
+        Ok(T::deserialize(self)?)
+    }
+}
+
+/// Why an item couldn't be converted to or from a [Value].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertError(String);
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl ser::Error for ConvertError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ConvertError(msg.to_string())
+    }
+}
+
+impl de::Error for ConvertError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ConvertError(msg.to_string())
+    }
+}
+
+/// Serializes an item into a [Value].
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, b: bool) -> Result<Value, ConvertError> {
+        Ok(Value::Bool(b))
+    }
+
+    fn serialize_i8(self, i: i8) -> Result<Value, ConvertError> {
+        self.serialize_i64(i.into())
+    }
+
+    fn serialize_i16(self, i: i16) -> Result<Value, ConvertError> {
+        self.serialize_i64(i.into())
+    }
+
+    fn serialize_i32(self, i: i32) -> Result<Value, ConvertError> {
+        self.serialize_i64(i.into())
+    }
+
+    fn serialize_i64(self, i: i64) -> Result<Value, ConvertError> {
+        Ok(Value::Int(i))
+    }
+
+    fn serialize_u8(self, u: u8) -> Result<Value, ConvertError> {
+        self.serialize_i64(u.into())
+    }
+
+    fn serialize_u16(self, u: u16) -> Result<Value, ConvertError> {
+        self.serialize_i64(u.into())
+    }
+
+    fn serialize_u32(self, u: u32) -> Result<Value, ConvertError> {
+        self.serialize_i64(u.into())
+    }
+
+    fn serialize_u64(self, u: u64) -> Result<Value, ConvertError> {
+        Ok(i64::try_from(u).map_or(Value::Float(u as f64), Value::Int))
+    }
+
+    fn serialize_f32(self, f: f32) -> Result<Value, ConvertError> {
+        self.serialize_f64(f.into())
+    }
+
+    fn serialize_f64(self, f: f64) -> Result<Value, ConvertError> {
+        Ok(Value::Float(f))
+    }
+
+    fn serialize_char(self, c: char) -> Result<Value, ConvertError> {
+        Ok(Value::String(c.into()))
+    }
+
+    fn serialize_str(self, s: &str) -> Result<Value, ConvertError> {
+        Ok(Value::String(s.into()))
+    }
+
+    fn serialize_bytes(self, bytes: &[u8]) -> Result<Value, ConvertError> {
+        Ok(Value::Array(
+            bytes.iter().map(|&b| Value::Int(b.into())).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Value, ConvertError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, ConvertError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, ConvertError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, ConvertError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, ConvertError> {
+        Ok(Value::String(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, ConvertError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, ConvertError> {
+        let mut map = Value::map();
+        map.insert(variant, value.serialize(self)?);
+        Ok(map)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, ConvertError> {
+        Ok(SerializeArray {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, ConvertError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, ConvertError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, ConvertError> {
+        Ok(SerializeArray {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, ConvertError> {
+        Ok(SerializeMap {
+            variant: None,
+            map: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap, ConvertError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeMap, ConvertError> {
+        Ok(SerializeMap {
+            variant: Some(variant),
+            map: BTreeMap::new(),
+            key: None,
+        })
+    }
+}
+
+/// Wraps the value of an enum variant as `{"Variant": value}`.
+fn wrap_variant(variant: Option<&'static str>, value: Value) -> Value {
+    match variant {
+        Some(variant) => {
+            let mut map = Value::map();
+            map.insert(variant, value);
+            map
+        }
+        None => value,
+    }
+}
+
+/// Collects a sequence, tuple or tuple variant into a [Value::Array].
+struct SerializeArray {
+    variant: Option<&'static str>,
+    items: Vec<Value>,
+}
+
+impl SerializeArray {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, ConvertError> {
+        Ok(wrap_variant(self.variant, Value::Array(self.items)))
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeArray {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        self.finish()
+    }
+}
+
+/// Collects a map, struct or struct variant into a [Value::Map].
+struct SerializeMap {
+    variant: Option<&'static str>,
+    map: BTreeMap<String, Value>,
+    key: Option<String>,
+}
+
+impl SerializeMap {
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), ConvertError> {
+        self.map
+            .insert(key.into(), value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, ConvertError> {
+        Ok(wrap_variant(self.variant, Value::Map(self.map)))
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ConvertError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ConvertError("map value without a key".into()))?;
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ConvertError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ConvertError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        self.finish()
+    }
+}
+
+/// Serializes the key of a map, which must be a string, a number or a unit
+/// variant, as JSON only allows string keys.
+struct KeySerializer;
+
+/// Rejects a map key that can't be written as a string.
+fn bad_key() -> ConvertError {
+    ConvertError("map keys must be strings or numbers".into())
+}
+
+/// Serializes numbers and other scalar keys as a string.
+macro_rules! serialize_keys_as_strings {
+    ($($method:ident: $type:ty),*) => {
+        $(
+            fn $method(self, n: $type) -> Result<String, ConvertError> {
+                Ok(n.to_string())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = ConvertError;
+
+    type SerializeSeq = Impossible<String, ConvertError>;
+    type SerializeTuple = Impossible<String, ConvertError>;
+    type SerializeTupleStruct = Impossible<String, ConvertError>;
+    type SerializeTupleVariant = Impossible<String, ConvertError>;
+    type SerializeMap = Impossible<String, ConvertError>;
+    type SerializeStruct = Impossible<String, ConvertError>;
+    type SerializeStructVariant = Impossible<String, ConvertError>;
+
+    serialize_keys_as_strings!(
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char
+    );
+
+    fn serialize_str(self, s: &str) -> Result<String, ConvertError> {
+        Ok(s.into())
+    }
+
+    fn serialize_bytes(self, _bytes: &[u8]) -> Result<String, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_none(self) -> Result<String, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_unit(self) -> Result<String, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, ConvertError> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, ConvertError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, ConvertError> {
+        Err(bad_key())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, ConvertError> {
+        Err(bad_key())
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = ConvertError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Int(i) => visitor.visit_i64(i),
+            Value::Float(f) => visitor.visit_f64(f),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Array(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Map(map) => {
+                let mut map = MapDeserializer::new(map.into_iter().map(|(k, v)| (Key(k), v)));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        let (variant, value) = match self {
+            Value::String(variant) => (variant, None),
+            Value::Map(map) if map.len() == 1 => {
+                let (variant, value) = map.into_iter().next().unwrap();
+                (variant, Some(value))
+            }
+            _ => {
+                return Err(ConvertError(
+                    "expected a variant name or a map with a single key".into(),
+                ))
+            }
+        };
+
+        visitor.visit_enum(Variant { variant, value })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ConvertError> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+/// The variant of an enum being deserialized, along with its value unless
+/// it's a unit variant.
+struct Variant {
+    variant: String,
+    value: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for Variant {
+    type Error = ConvertError;
+    type Variant = VariantValue;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantValue), ConvertError> {
+        let variant = seed.deserialize(Value::String(self.variant))?;
+        Ok((variant, VariantValue(self.value)))
+    }
+}
+
+/// The value of the variant of an enum being deserialized.
+struct VariantValue(Option<Value>);
+
+impl<'de> VariantAccess<'de> for VariantValue {
+    type Error = ConvertError;
+
+    fn unit_variant(self) -> Result<(), ConvertError> {
+        match self.0 {
+            None | Some(Value::Null) => Ok(()),
+            Some(_) => Err(ConvertError("expected a unit variant".into())),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ConvertError> {
+        seed.deserialize(self.0.unwrap_or_default())
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        de::Deserializer::deserialize_seq(self.0.unwrap_or_default(), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        de::Deserializer::deserialize_map(self.0.unwrap_or_default(), visitor)
+    }
+}
+
+/// The key of a [Value::Map], which is parsed when a number is expected, as
+/// numeric keys are saved as strings.
+struct Key(String);
+
+impl<'de> IntoDeserializer<'de, ConvertError> for Key {
+    type Deserializer = Key;
+
+    fn into_deserializer(self) -> Key {
+        self
+    }
+}
+
+/// Parses a key when a number or other scalar is expected, falling back to
+/// the key as a string.
+macro_rules! deserialize_parsed_keys {
+    ($($method:ident => $visit:ident: $type:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+                match self.0.parse::<$type>() {
+                    Ok(n) => visitor.$visit(n),
+                    Err(_) => visitor.visit_string(self.0),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Key {
+    type Error = ConvertError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        visitor.visit_string(self.0)
+    }
+
+    deserialize_parsed_keys!(
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64
+    );
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        de::Deserializer::deserialize_enum(Value::String(self.0), name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Line(i64, i64),
+        Rect { w: f64, h: f64 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        id: String,
+        name: Option<String>,
+        shapes: Vec<Shape>,
+        counts: HashMap<i64, u8>,
+        speed: f64,
+    }
+
+    /// Tests items keep their JSON shape, and survive a round trip through
+    /// [Value] along with floats JSON can't represent
+    #[test]
+    fn round_trip() {
+        let item = Item {
+            id: Uuid::new_v4().to_string(),
+            name: None,
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(f64::INFINITY),
+                Shape::Line(1, 2),
+                Shape::Rect { w: 1.5, h: -0.0 },
+            ],
+            counts: [(-1, 2), (3, 4)].into_iter().collect(),
+            speed: f64::NEG_INFINITY,
+        };
+
+        let value = Value::serialize_from(&item).unwrap();
+        let json = serde_json::to_value(&item).unwrap();
+        assert_eq!(serde_json::Value::from(value.clone()), json);
+
+        assert_eq!(value.deserialize_into::<Item>().unwrap(), item);
+
+        let nan = Value::serialize_from(&f64::NAN).unwrap();
+        assert!(nan.deserialize_into::<f64>().unwrap().is_nan());
+
+        let mut bad = HashMap::new();
+        bad.insert(vec![1], 1);
+        assert!(Value::serialize_from(&bad).is_err());
+        assert!(Value::from("x").deserialize_into::<i64>().is_err());
+    }
+}
@@ -1,14 +1,95 @@
+use crate::history::{Edit, History};
+use crate::observer::Observers;
 use crate::prelude::*;
+use crate::relation::References;
+use crate::search::SearchIndex;
+use crate::transaction::Transaction;
+use std::ops::RangeBounds;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Implemented by any type that can be stored inside of a [Database], providing
+/// the primary key used to index it.
+///
+/// The key should be unique per item and must not change for the lifetime of
+/// the item inside of the database; [Record] uses its `uuid`.
+pub trait Keyed {
+    /// The type of the primary key, e.g. a [String] or an integer id.
+    type Key: hash::Hash + Eq + Clone + Serialize + DeserializeOwned;
+
+    /// Returns the primary key of this item.
+    fn primary_key(&self) -> Self::Key;
+
+    /// Secondary indexes every [Database] of this type is created with. These
+    /// are rebuilt whenever a database is loaded with [Database::from].
+    ///
+    /// Defaults to no indexes.
+    fn indexes() -> Vec<(&'static str, IndexFn<Self>)>
+    where
+        Self: Sized,
+    {
+        Vec::new()
+    }
+
+    /// The name of this type, stored in the header of saves made with
+    /// [Database::dump_db] and checked when loading them again.
+    ///
+    /// Defaults to [std::any::type_name], which includes the module path, so
+    /// should be overridden if the type could be moved or renamed.
+    fn type_name() -> &'static str
+    where
+        Self: Sized,
+    {
+        std::any::type_name::<Self>()
+    }
+
+    /// Checks an item against the [Schema]s registered with
+    /// [Database::define_model] before it is created or updated, filling in
+    /// any defaults. See [crate::schema].
+    ///
+    /// Defaults to accepting every item, while [Record] checks its attributes
+    /// against the schema of its model.
+    fn validate(&mut self, _schemas: &HashMap<String, Schema>) -> Result<(), DatabaseError> {
+        Ok(())
+    }
+
+    /// The keys of the items this item refers to, along with the name of each
+    /// relation. These are indexed so [Database::referrers] can find the items
+    /// referring to an item. See [crate::relation].
+    ///
+    /// Defaults to no references, while [Record] uses its links.
+    fn references(&self) -> Vec<(String, Self::Key)> {
+        Vec::new()
+    }
+
+    /// Removes the reference through a relation, when the item it refers to is
+    /// destroyed with [OnDestroy::Nullify].
+    ///
+    /// Defaults to doing nothing.
+    fn clear_reference(&mut self, _relation: &str) {}
+
+    /// If the reference through a relation may point at `target`, such as a
+    /// [Record] checking the model of its link matches.
+    ///
+    /// Defaults to allowing any item.
+    fn refers_to(&self, _relation: &str, _target: &Self) -> bool {
+        true
+    }
+}
 
 /// The primary database structure, allowing storage of a generic type with
 /// dumping/saving options avalible.
 ///
 /// The generic type used should primarily be structures as they resemble a
-/// conventional database model and should implament [hash::Hash] and [Eq] for
+/// conventional database model and should implament [Keyed] and [PartialEq] for
 /// basic in-memory storage with [Serialize] and [Deserialize] being implamented
 /// for file operations involving the database (these are also required).
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Database<T: hash::Hash + Eq> {
+///
+/// Observers registered with [Database::observe] are left out of [Clone] and
+/// [PartialEq]: a cloned database starts without any observers, and two
+/// databases compare equal regardless of their observers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Database<T: Keyed> {
     /// Friendly name for the database, preferibly in `slug-form-like-this` as
     /// this is the fallback path
     ///
@@ -28,11 +109,76 @@ pub struct Database<T: hash::Hash + Eq> {
     /// duplicates, it just doesn't flag an error.
     pub strict_dupes: bool,
 
-    /// In-memory [HashSet] of all items
-    pub items: HashSet<T>,
+    /// How many previous saves [Database::dump_db] keeps as numbered backups
+    /// next to the save file, e.g. `save.gddb.1` for the most recent. Setting
+    /// this as `0` keeps no backups.
+    ///
+    /// Backups can be loaded again with [Database::restore_backup].
+    pub backups: usize,
+
+    /// If every change should be appended to a journal next to the save path
+    /// as it happens, so it's persisted without calling [Database::dump_db].
+    /// See [crate::journal].
+    #[serde(default)]
+    pub journal: bool,
+
+    /// How many steps of history are kept for [Database::undo], setting this
+    /// as `0` records no history. See [crate::history].
+    #[serde(default)]
+    pub history_depth: usize,
+
+    /// The [Format] [Database::dump_db] saves in. If [Option::None], the format
+    /// is inferred from the extension of the save path with [Format::from_path].
+    #[serde(skip)]
+    pub format: Option<Format>,
+
+    /// In-memory [HashMap] of all items, indexed by their [Keyed::primary_key]
+    pub items: HashMap<T::Key, T>,
+
+    /// The open transactions, see [Database::begin]
+    #[serde(skip, default = "Option::default")]
+    pub(crate) transaction: Option<Transaction<T>>,
+
+    /// The undo and redo history, see [Database::undo]
+    #[serde(skip, default = "Option::default")]
+    pub(crate) history: Option<History<T>>,
+
+    /// Callbacks notified of every change, see [Database::observe]. These
+    /// aren't cloned or compared
+    #[serde(skip, default = "Observers::new")]
+    pub(crate) observers: Observers<T>,
+
+    /// Named secondary indexes, see [Database::create_index]
+    #[serde(skip, default = "HashMap::new")]
+    indexes: HashMap<String, Index<T>>,
+
+    /// Named ordered indexes, see [Database::create_ordered_index]
+    #[serde(skip, default = "HashMap::new")]
+    ordered_indexes: HashMap<String, OrderedIndex<T>>,
+
+    /// Named unique constraints, see [Database::create_unique]
+    #[serde(skip, default = "HashMap::new")]
+    pub(crate) unique_constraints: HashMap<String, OrderedIndex<T>>,
+
+    /// The reverse index of every reference, see [Database::referrers]
+    #[serde(skip, default = "References::new")]
+    pub(crate) references: References<T>,
+
+    /// What happens when an item referred to is destroyed, by relation. See
+    /// [Database::on_destroy]
+    #[serde(skip, default = "HashMap::new")]
+    pub(crate) relation_rules: HashMap<String, OnDestroy>,
+
+    /// The full-text search index, see [Database::enable_search_with]
+    #[serde(skip, default = "Option::default")]
+    pub(crate) search: Option<SearchIndex<T>>,
+
+    /// The schema of each model, see [Database::define_model]
+    #[serde(skip, default = "HashMap::new")]
+    pub(crate) schemas: HashMap<String, Schema>,
 }
 
-impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
     /// Creates a new database instance from given parameters.
     ///
     /// - To add a first item, use [Database::create].
@@ -42,18 +188,46 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
         save_path: impl Into<Option<PathBuf>>,
         strict_dupes: bool,
     ) -> Self {
-        Database {
+        let mut db = Database {
             label: label.into(),
             save_path: save_path.into(),
             strict_dupes,
-            items: HashSet::new(),
-        }
+            backups: 0,
+            journal: false,
+            history_depth: 0,
+            format: None,
+            items: HashMap::new(),
+            indexes: HashMap::new(),
+            ordered_indexes: HashMap::new(),
+            unique_constraints: HashMap::new(),
+            references: References::new(),
+            relation_rules: HashMap::new(),
+            search: None,
+            schemas: HashMap::new(),
+            transaction: None,
+            history: None,
+            observers: Observers::new(),
+        };
+
+        db.create_default_indexes();
+        db
     }
 
     /// Creates a database from a `.gddb` file.
     ///
     /// This retrives a dump file (saved database) from the path given and loads
-    /// it as the [Database] structure.
+    /// it as the [Database] structure, in the [Format] inferred from the
+    /// extension of the path. Any journal next to the save is replayed on top
+    /// of it, see [crate::journal]. Items saved with an older
+    /// [Database::schema_version] are upgraded with the registered migrations,
+    /// see [crate::migration].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::CorruptFile] if the file isn't a valid save,
+    /// [DatabaseError::TypeMismatch] if it stores a different type and
+    /// [DatabaseError::VersionMismatch] if it was saved by a newer version of
+    /// GDDB or with a newer [Database::schema_version].
     ///
     /// # Examples
     ///
@@ -85,8 +259,65 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     /// }
     /// ```
     pub fn from(path: impl Into<PathBuf>) -> Result<Self, DatabaseError> {
-        let stream = get_stream_from_path(path.into())?;
-        let decoded: Database<Record> = bincode::deserialize(&stream[..]).unwrap();
+        let path = path.into();
+        let format = Format::from_path(&path);
+        let mut db = Self::decode(&get_stream_from_path(path.clone())?, format)?;
+
+        db.replay_journal(&path)?;
+        Ok(db)
+    }
+
+    /// Creates a database from a file saved in the given [Format], regardless
+    /// of its extension. The database will keep saving in that format.
+    ///
+    /// See [Database::from] for the errors this can return.
+    pub fn from_with_format(
+        path: impl Into<PathBuf>,
+        format: Format,
+    ) -> Result<Self, DatabaseError> {
+        let path = path.into();
+        let mut db = Self::decode(&get_stream_from_path(path.clone())?, format)?;
+
+        db.replay_journal(&path)?;
+        db.format = Some(format);
+        Ok(db)
+    }
+
+    /// Decodes a database from a file made by [Database::encode], migrating
+    /// its items to the current [Database::schema_version].
+    fn decode(stream: &[u8], format: Format) -> Result<Self, DatabaseError> {
+        let (header, value) = format.decode(stream)?;
+
+        if header.type_name != Record::type_name() {
+            return Err(DatabaseError::TypeMismatch(header.type_name));
+        }
+
+        Self::from_value(header.schema_version, value)
+    }
+
+    /// Converts a database from its [Value] form, as returned by
+    /// [Database::to_value] with the given [Database::schema_version],
+    /// migrating its items and rebuilding its indexes.
+    pub(crate) fn from_value(schema_version: u32, mut value: Value) -> Result<Self, DatabaseError> {
+        if schema_version > Self::schema_version() {
+            return Err(DatabaseError::VersionMismatch(schema_version));
+        }
+
+        if let Some(Value::Map(items)) = value.get_mut("items") {
+            for item in items.values_mut() {
+                *item = Self::migrate(schema_version, std::mem::take(item));
+            }
+        }
+
+        let mut decoded: Database<Record> = value
+            .deserialize_into()
+            .map_err(|_| DatabaseError::CorruptFile)?;
+        let items = std::mem::take(&mut decoded.items);
+
+        decoded.create_default_indexes();
+        for item in items.into_values() {
+            decoded.put_item(item);
+        }
 
         Ok(decoded)
     }
@@ -96,27 +327,136 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     /// If this is the first item added to the database, please ensure it's the
     /// only type you'd like to add. Due to generics, the first item you add
     /// will be set as the type to use (unless removed).
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::DupeFound] if an item with the same
+    /// [Keyed::primary_key] already exists, unless it is identical and
+    /// [Database::strict_dupes] is disabled,
+    /// [DatabaseError::ValidationFailed] if it fails [Keyed::validate],
+    /// [DatabaseError::UniqueViolation] if it breaks a unique constraint, or
+    /// [DatabaseError::BrokenReference] if it refers to an item that doesn't
+    /// exist.
     pub fn create(&mut self, item: Record) -> Result<(), DatabaseError> {
-        if self.strict_dupes {
-            if self.items.contains(&item) {
+        let item = self.validated(item)?;
+        let key = item.primary_key();
+
+        if let Some(existing) = self.items.get(&key) {
+            if self.strict_dupes || existing != &item {
                 return Err(DatabaseError::DupeFound);
             }
+
+            return Ok(());
         }
 
-        self.items.insert(item);
+        self.check_unique(&item, &[])?;
+        self.check_references(&item)?;
+        self.insert_item(item)?;
         return Ok(());
     }
 
+    /// Inserts an item, replacing any existing item with the same
+    /// [Keyed::primary_key].
+    ///
+    /// Returns the item that was replaced, if any. See [Database::create] for
+    /// the errors this can return.
+    pub fn upsert(&mut self, item: Record) -> Result<Option<Record>, DatabaseError> {
+        let item = self.validated(item)?;
+        self.check_unique(&item, &[&item.primary_key()])?;
+        self.check_references(&item)?;
+        self.insert_item(item)
+    }
+
     /// Replaces an item inside of the database with another
     /// item, used for updating/replacing items easily.
     ///
     /// [Database::update] can be used in conjunction to find and replace
     /// values individually if needed.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ItemNotFound] if the item isn't in the
+    /// database, [DatabaseError::RelationRestricted] if the
+    /// [Keyed::primary_key] changes while other items refer to it, or any
+    /// error [Database::create] can return.
     pub fn update(&mut self, item: &Record, new: Record) -> Result<(), DatabaseError> {
-        self.destroy(item)?;
-        self.create(new)?;
+        if !self.contains(item) {
+            return Err(DatabaseError::ItemNotFound);
+        }
 
-        Ok(())
+        let new = self.validated(new)?;
+        let key = item.primary_key();
+        let new_key = new.primary_key();
+
+        if new_key != key && self.items.contains_key(&new_key) {
+            return Err(DatabaseError::DupeFound);
+        }
+
+        self.check_unique(&new, &[&key])?;
+        self.check_references(&new)?;
+
+        if new_key != key {
+            self.check_referrers(&key)?;
+        }
+
+        self.transaction(|db| {
+            db.insert_item(new)?;
+
+            if new_key != key {
+                db.remove_item(&key)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Changes an item in place by its [Keyed::primary_key], returning the
+    /// result of `f`.
+    ///
+    /// Unlike changing an item through [Database::get_mut], indexes, the
+    /// journal, the history and observers all see the change as if it were
+    /// made with [Database::update]. Nothing is recorded if `f` leaves the
+    /// item unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::{Database, Record, Value};
+    ///
+    /// fn main() {
+    ///     let record = Record::new("Player".into());
+    ///     let mut db = Database::new("GAME", None, false);
+    ///
+    ///     db.create(record.clone()).unwrap();
+    ///     db.modify(&record.uuid, |player| player.set("hp", 10)).unwrap();
+    ///
+    ///     assert_eq!(db.get(&record.uuid).unwrap().get("hp"), Some(&Value::Int(10)));
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ItemNotFound] if no item has the given key,
+    /// or [DatabaseError::DupeFound] if `f` changes the key to one that's
+    /// already taken.
+    pub fn modify<Q, R>(
+        &mut self,
+        key: &Q,
+        f: impl FnOnce(&mut Record) -> R,
+    ) -> Result<R, DatabaseError>
+    where
+        Record::Key: Borrow<Q>,
+        Q: hash::Hash + Eq + ?Sized,
+    {
+        let old = self.get(key)?.clone();
+        let mut new = old.clone();
+        let result = f(&mut new);
+
+        if new != old {
+            self.update(&old, new)?;
+        }
+
+        Ok(result)
     }
 
     /// Loads database from existant path or creates a new one if it doesn't already
@@ -133,6 +473,10 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     /// name of `y`, not `x` so therefore it is recommended to have a database
     /// path with `x.gddb` or `x.db` only.
     ///
+    /// If there's no save yet but there's a journal next to the path, a new
+    /// database is created with journaling enabled and the journal replayed on
+    /// top of it, see [crate::journal].
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -164,7 +508,9 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
                 None => return Err(DatabaseError::BadDbName),
             };
 
-            Ok(Database::new(db_name, Some(path_into), strict_dupes))
+            let mut db = Database::new(db_name, Some(path_into.clone()), strict_dupes);
+            db.journal = db.replay_journal(&path_into)?;
+            Ok(db)
         }
     }
 
@@ -173,16 +519,139 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     /// See [Database::update] if you'd like to update/replace an item easily,
     /// rather than individually deleting and adding.
     ///
+    /// Items referring to it are handled by the [OnDestroy] rule of each
+    /// relation, see [crate::relation].
+    ///
     /// # Errors
     ///
     /// Will return [DatabaseError::ItemNotFound] if the item that is attempting
-    /// to be deleted was not found.
+    /// to be deleted was not found, or [DatabaseError::RelationRestricted] if
+    /// a relation referring to it stops it being destroyed.
     pub fn destroy(&mut self, item: &Record) -> Result<(), DatabaseError> {
-        if self.items.remove(item) {
-            Ok(())
-        } else {
-            Err(DatabaseError::ItemNotFound)
+        if !self.contains(item) {
+            return Err(DatabaseError::ItemNotFound);
         }
+
+        self.destroy_item(&item.primary_key())?;
+        Ok(())
+    }
+
+    /// Removes an item from the database by its [Keyed::primary_key],
+    /// returning the removed item, like [Database::destroy].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ItemNotFound] if no item has the given key,
+    /// or [DatabaseError::RelationRestricted] if a relation referring to it
+    /// stops it being destroyed.
+    pub fn remove_by_key<Q>(&mut self, key: &Q) -> Result<Record, DatabaseError>
+    where
+        Record::Key: Borrow<Q>,
+        Q: hash::Hash + Eq + ?Sized,
+    {
+        self.destroy_item(key)?.ok_or(DatabaseError::ItemNotFound)
+    }
+
+    /// Removes every item where a field matches a value, using the same
+    /// syntax as [Database::query], returning the number removed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::{Database, Record};
+    ///
+    /// fn main() {
+    ///     let record = Record::new("Player".into());
+    ///     let mut db = Database::new("GAME", None, false);
+    ///
+    ///     db.create(record.clone()).unwrap();
+    ///
+    ///     assert_eq!(db.destroy_by(|r| &r.uuid, record.uuid).unwrap(), 1);
+    ///     assert_eq!(db.len(), 0);
+    /// }
+    /// ```
+    pub fn destroy_by<Q: PartialEq, V: Fn(&Record) -> &Q>(
+        &mut self,
+        value: V,
+        query: Q,
+    ) -> Result<usize, DatabaseError> {
+        self.destroy_where(|item| value(item) == &query)
+    }
+
+    /// Removes every item matching a predicate as a single transaction,
+    /// returning the number removed by it. Items destroyed because they
+    /// referred to one of them aren't counted, even if they also matched, see
+    /// [Database::destroy].
+    pub fn destroy_where(
+        &mut self,
+        predicate: impl Fn(&Record) -> bool,
+    ) -> Result<usize, DatabaseError> {
+        let keys: Vec<Record::Key> = self
+            .items
+            .iter()
+            .filter(|(_, item)| predicate(item))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        self.transaction(|db| {
+            let mut count = 0;
+
+            for key in &keys {
+                if db.destroy_item(key)?.is_some() {
+                    count += 1;
+                }
+            }
+
+            Ok(count)
+        })
+    }
+
+    /// Retrieves an item by its [Keyed::primary_key] without scanning the
+    /// database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::{Database, Record};
+    ///
+    /// fn main() {
+    ///     let record = Record::new("Player".into());
+    ///     let mut db = Database::new("GAME", None, false);
+    ///
+    ///     db.create(record.clone()).unwrap();
+    ///
+    ///     assert_eq!(db.get(&record.uuid).unwrap(), &record);
+    /// }
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Result<&Record, DatabaseError>
+    where
+        Record::Key: Borrow<Q>,
+        Q: hash::Hash + Eq + ?Sized,
+    {
+        self.items.get(key).ok_or(DatabaseError::ItemNotFound)
+    }
+
+    /// Mutably retrieves an item by its [Keyed::primary_key].
+    ///
+    /// Changes made through the returned reference bypass everything
+    /// [Database::modify] keeps up to date, so they are:
+    ///
+    /// - Not reflected in any [Index], ordered index, unique constraint,
+    ///   search index or the reverse index of references.
+    /// - Not validated against a schema or checked by unique constraints.
+    /// - Not written to the journal, so they're lost on a crash until the next
+    ///   [Database::dump_db].
+    /// - Not recorded in the undo history or rolled back by a transaction.
+    /// - Not sent to observers or live queries.
+    ///
+    /// The primary key and any field used by those indexes must not be changed
+    /// this way, use [Database::modify] or [Database::update] instead.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Result<&mut Record, DatabaseError>
+    where
+        Record::Key: Borrow<Q>,
+        Q: hash::Hash + Eq + ?Sized,
+    {
+        self.items.get_mut(key).ok_or(DatabaseError::ItemNotFound)
     }
 
     /// Dumps/saves database to a binary file.
@@ -195,11 +664,82 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     ///
     /// You can also overwrite this behaviour by defining a [Database::save_path]
     /// when generating the database inside of [Database::new].
+    ///
+    /// # Crash safety
+    ///
+    /// The dump is written to a temporary file next to the save path, flushed
+    /// to disk and then renamed over the previous save, so an interrupted dump
+    /// never leaves a partially written save behind. If [Database::backups] is
+    /// set, the previous save is kept as a numbered backup first.
+    ///
+    /// # File format
+    ///
+    /// The database is saved in [Database::format], or the format inferred
+    /// from the extension of the save path. Saves record the current
+    /// [Database::schema_version], and binary formats start with a
+    /// [FileHeader] and checksum, so damaged or outdated saves are detected by
+    /// [Database::from].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::TransactionOpen] if a transaction is open,
+    /// as its changes may still be rolled back.
     pub fn dump_db(&self) -> Result<(), DatabaseError> {
-        let mut dump_file = self.open_db_path()?;
-        bincode::serialize_into(&mut dump_file, self).unwrap();
+        self.dump_db_as(self.smart_format_get())
+    }
 
-        Ok(())
+    /// Dumps/saves database like [Database::dump_db], but in the given
+    /// [Format] rather than [Database::format].
+    ///
+    /// The journal is removed once the save is on disk, see [crate::journal].
+    pub fn dump_db_as(&self, format: Format) -> Result<(), DatabaseError> {
+        write_atomic(&self.smart_path_get(), &self.encode(format)?, self.backups)?;
+        self.clear_journal()
+    }
+
+    /// Writes a snapshot of the database to another path in the given
+    /// [Format], without changing [Database::save_path] or making backups.
+    ///
+    /// This is useful for exporting a human-readable copy with [Format::Json]
+    /// for debugging or modding.
+    pub fn export_db(&self, path: impl Into<PathBuf>, format: Format) -> Result<(), DatabaseError> {
+        write_atomic(&path.into(), &self.encode(format)?, 0)
+    }
+
+    /// Replaces all items with those from a numbered backup made by
+    /// [Database::dump_db], where `1` is the most recent backup.
+    ///
+    /// Only the in-memory database and journal are changed, call
+    /// [Database::dump_db] afterwards to overwrite the save file with the
+    /// restored items.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::DatabaseNotFound] if the backup doesn't
+    /// exist.
+    pub fn restore_backup(&mut self, n: usize) -> Result<(), DatabaseError> {
+        if n == 0 {
+            return Err(DatabaseError::DatabaseNotFound);
+        }
+
+        let backup: Database<Record> = Database::from_with_format(
+            backup_path(&self.smart_path_get(), n),
+            self.smart_format_get(),
+        )?;
+
+        let keys: Vec<Record::Key> = self.items.keys().cloned().collect();
+
+        self.transaction(|db| {
+            for key in keys {
+                db.remove_item(&key)?;
+            }
+
+            for item in backup.items.into_values() {
+                db.insert_item(item)?;
+            }
+
+            Ok(())
+        })
     }
 
     /// Query the database for a specific item.
@@ -218,13 +758,21 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     ///
     /// ```rust
     /// use serde::{Serialize, Deserialize};
-    /// use gddb::Database;
+    /// use gddb::{Database, Keyed};
     ///
     /// #[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize, Clone)]
     /// struct ExampleStruct {
     ///     my_age: i32
     /// }
     ///
+    /// impl Keyed for ExampleStruct {
+    ///     type Key = i32;
+    ///
+    ///     fn primary_key(&self) -> i32 {
+    ///         self.my_age
+    ///     }
+    /// }
+    ///
     /// fn main() {
     ///     let my_struct = ExampleStruct { my_age: 329 };
     ///     let mut my_db = Database::new("query_test", None, false);
@@ -241,7 +789,7 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
         value: V,
         query: Q,
     ) -> Result<&Record, DatabaseError> {
-        for item in self.items.iter() {
+        for item in self.items.values() {
             if value(item).eq(&query) {
                 return Ok(item);
             }
@@ -266,7 +814,7 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     ///
     /// ```rust
     /// use serde::{Serialize, Deserialize};
-    /// use gddb::Database;
+    /// use gddb::{Database, Keyed};
     ///
     /// #[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize, Clone)]
     /// struct ExampleStruct {
@@ -274,6 +822,14 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     ///     age: i32,
     /// }
     ///
+    /// impl Keyed for ExampleStruct {
+    ///     type Key = String;
+    ///
+    ///     fn primary_key(&self) -> String {
+    ///         self.uuid.clone()
+    ///     }
+    /// }
+    ///
     /// fn main() {
     ///     let mut my_db = Database::new("query_test", None, false);
     ///
@@ -292,7 +848,7 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
         query: Q,
     ) -> Result<Vec<&Record>, DatabaseError> {
         let mut items: Vec<&Record> = vec![];
-        for item in self.items.iter() {
+        for item in self.items.values() {
             if value(item) == &query {
                 items.push(item);
             }
@@ -305,15 +861,181 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
         Err(DatabaseError::ItemNotFound)
     }
 
+    /// Declares a named secondary index, built from all current items and kept
+    /// in sync by [Database::create], [Database::update] and
+    /// [Database::destroy]. An existing index with the same name is replaced.
+    ///
+    /// Indexes aren't saved by [Database::dump_db] so must be declared again
+    /// after loading, unless returned by [Keyed::indexes].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::{Database, Record};
+    ///
+    /// fn main() {
+    ///     let mut db: Database<Record> = Database::new("GAME", None, false);
+    ///
+    ///     db.create_index("by_attributes", |r: &Record| r.attributes.to_string());
+    ///     db.create(Record::new("Player".into())).unwrap();
+    ///
+    ///     assert_eq!(db.query_index("by_attributes", "{}").unwrap().len(), 1);
+    /// }
+    /// ```
+    pub fn create_index(&mut self, name: impl Into<String>, extract: IndexFn<Record>) {
+        let mut index = Index::new(extract);
+
+        for item in self.items.values() {
+            index.insert(item);
+        }
+
+        self.indexes.insert(name.into(), index);
+    }
+
+    /// Declares a named [OrderedIndex], built from all current items and kept
+    /// in sync like [Database::create_index]. An existing ordered index with
+    /// the same name is replaced.
+    ///
+    /// Ordered indexes answer [Database::query_range],
+    /// [Database::query_prefix], [Database::index_min] and
+    /// [Database::index_max] without scanning the database. For [Record]s,
+    /// [Database::create_path_index] indexes an attribute path.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::{Database, Record, Value};
+    ///
+    /// fn main() {
+    ///     let mut db: Database<Record> = Database::new("GAME", None, false);
+    ///     db.create_ordered_index("by_uuid", |r: &Record| Value::from(r.uuid.as_str()));
+    ///
+    ///     let record = Record::new("Player".into());
+    ///     db.create(record.clone()).unwrap();
+    ///
+    ///     assert_eq!(db.index_min("by_uuid").unwrap(), vec![&record]);
+    /// }
+    /// ```
+    pub fn create_ordered_index(
+        &mut self,
+        name: impl Into<String>,
+        extract: impl Fn(&Record) -> Value + Send + Sync + 'static,
+    ) {
+        let mut index = OrderedIndex::new(Arc::new(extract));
+
+        for item in self.items.values() {
+            index.insert(item);
+        }
+
+        self.ordered_indexes.insert(name.into(), index);
+    }
+
+    /// Removes a named secondary or ordered index.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::IndexNotFound] if no index has the given name.
+    pub fn drop_index(&mut self, name: &str) -> Result<(), DatabaseError> {
+        let dropped = self.indexes.remove(name).is_some();
+        let dropped_ordered = self.ordered_indexes.remove(name).is_some();
+
+        if !dropped && !dropped_ordered {
+            return Err(DatabaseError::IndexNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Query a named [OrderedIndex] for all items with a value inside of
+    /// `range`, smallest value first. Use `.rev()` on the results for the
+    /// largest first, such as for a leaderboard.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::IndexNotFound] if no ordered index has the
+    /// given name.
+    pub fn query_range(
+        &self,
+        name: &str,
+        range: impl RangeBounds<Value>,
+    ) -> Result<Vec<&Record>, DatabaseError> {
+        let index = self.ordered_index(name)?;
+
+        Ok(self.keys_to_items(index.range(range)))
+    }
+
+    /// Query a named [OrderedIndex] for all items with a string value
+    /// starting with `prefix`, in order.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::IndexNotFound] if no ordered index has the
+    /// given name.
+    pub fn query_prefix(&self, name: &str, prefix: &str) -> Result<Vec<&Record>, DatabaseError> {
+        let index = self.ordered_index(name)?;
+
+        Ok(self.keys_to_items(index.prefix(prefix)))
+    }
+
+    /// Returns every item sharing the smallest value in a named
+    /// [OrderedIndex].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::IndexNotFound] if no ordered index has the
+    /// given name, or [DatabaseError::ItemNotFound] if the index is empty.
+    pub fn index_min(&self, name: &str) -> Result<Vec<&Record>, DatabaseError> {
+        let items = self.keys_to_items(self.ordered_index(name)?.min());
+
+        if items.is_empty() {
+            return Err(DatabaseError::ItemNotFound);
+        }
+
+        Ok(items)
+    }
+
+    /// Returns every item sharing the largest value in a named
+    /// [OrderedIndex], see [Database::index_min].
+    pub fn index_max(&self, name: &str) -> Result<Vec<&Record>, DatabaseError> {
+        let items = self.keys_to_items(self.ordered_index(name)?.max());
+
+        if items.is_empty() {
+            return Err(DatabaseError::ItemNotFound);
+        }
+
+        Ok(items)
+    }
+
+    /// Query a named secondary index for all items with the given value,
+    /// without scanning the database.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::IndexNotFound] if no index has the given
+    /// name, or [DatabaseError::ItemNotFound] if no items match.
+    pub fn query_index(&self, name: &str, value: &str) -> Result<Vec<&Record>, DatabaseError> {
+        let index = self.indexes.get(name).ok_or(DatabaseError::IndexNotFound)?;
+        let items: Vec<&Record> = index
+            .lookup(value)
+            .filter_map(|key| self.items.get(key))
+            .collect();
+
+        if items.is_empty() {
+            return Err(DatabaseError::ItemNotFound);
+        }
+
+        Ok(items)
+    }
+
     /// Searches the database for a specific value. If it does not exist, this
     /// method will return [DatabaseError::ItemNotFound].
     ///
-    /// This is a wrapper around [HashSet::contains].
+    /// The item is looked up by its [Keyed::primary_key] and then compared.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use gddb::Database;
+    /// use gddb::{Database, Keyed};
     /// use serde::{Serialize, Deserialize};
     ///
     /// #[derive(Hash, Eq, PartialEq, Serialize, Deserialize, Copy, Clone)]
@@ -321,6 +1043,14 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     ///     item: i32
     /// }
     ///
+    /// impl Keyed for ExampleStruct {
+    ///     type Key = i32;
+    ///
+    ///     fn primary_key(&self) -> i32 {
+    ///         self.item
+    ///     }
+    /// }
+    ///
     /// fn main() {
     ///     let exp_struct = ExampleStruct { item: 4942 };
     ///     let mut db = Database::new("Contains example", None, false);
@@ -331,7 +1061,7 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     /// }
     /// ```
     pub fn contains(&self, query: &Record) -> bool {
-        self.items.contains(query)
+        self.items.get(&query.primary_key()) == Some(query)
     }
 
     /// Returns the number of database entries
@@ -341,7 +1071,7 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     /// # Examples
     ///
     /// ```rust
-    /// use gddb::Database;
+    /// use gddb::{Database, Keyed};
     /// use serde::{Serialize, Deserialize};
     ///
     /// #[derive(Hash, Eq, PartialEq, Serialize, Deserialize, Copy, Clone)]
@@ -349,6 +1079,14 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     ///     item: i32
     /// }
     ///
+    /// impl Keyed for ExampleStruct {
+    ///     type Key = i32;
+    ///
+    ///     fn primary_key(&self) -> i32 {
+    ///         self.item
+    ///     }
+    /// }
+    ///
     /// fn main() {
     ///     let exp_struct = ExampleStruct { item: 4942 };
     ///     let mut db = Database::new("Contains example", None, false);
@@ -362,20 +1100,161 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
         self.items.len() as i32
     }
 
-    /// Opens the path given in [Database::save_path] (or auto-generates a path).
-    fn open_db_path(&self) -> Result<File, DatabaseError> {
-        let definate_path = self.smart_path_get();
+    /// Inserts an item, returning the item it replaced. Every change made
+    /// through the public API goes through this or [Database::remove_item].
+    pub(crate) fn insert_item(&mut self, item: Record) -> Result<Option<Record>, DatabaseError> {
+        self.journal_insert(&item)?;
+
+        if !self.tracking_edits() {
+            return Ok(self.put_item(item));
+        }
+
+        let key = item.primary_key();
+        let after = Some(item.clone());
+        let before = self.put_item(item);
+
+        self.record_edit(Edit {
+            key,
+            before: before.clone(),
+            after,
+        });
+        Ok(before)
+    }
+
+    /// Removes an item, returning it if it existed.
+    pub(crate) fn remove_item<Q>(&mut self, key: &Q) -> Result<Option<Record>, DatabaseError>
+    where
+        Record::Key: Borrow<Q>,
+        Q: hash::Hash + Eq + ?Sized,
+    {
+        let key = match self.items.get_key_value(key) {
+            Some((key, _)) => key.clone(),
+            None => return Ok(None),
+        };
+
+        self.journal_remove(&key)?;
+
+        let before = self.take_item::<Record::Key>(&key);
+
+        if self.tracking_edits() {
+            self.record_edit(Edit {
+                key,
+                before: before.clone(),
+                after: None,
+            });
+        }
+
+        Ok(before)
+    }
+
+    /// Inserts an item into [Database::items] and every index, returning the
+    /// item it replaced.
+    pub(crate) fn put_item(&mut self, item: Record) -> Option<Record> {
+        let old = self.take_item(&item.primary_key());
+
+        for index in self.indexes.values_mut() {
+            index.insert(&item);
+        }
+
+        for index in self.ordered_indexes.values_mut() {
+            index.insert(&item);
+        }
+
+        for index in self.unique_constraints.values_mut() {
+            index.insert(&item);
+        }
+
+        self.references.insert(&item);
+
+        if let Some(search) = self.search.as_mut() {
+            search.insert(&item);
+        }
+
+        self.items.insert(item.primary_key(), item);
+        old
+    }
+
+    /// Removes an item from [Database::items] and every index.
+    pub(crate) fn take_item<Q>(&mut self, key: &Q) -> Option<Record>
+    where
+        Record::Key: Borrow<Q>,
+        Q: hash::Hash + Eq + ?Sized,
+    {
+        let old = self.items.remove(key)?;
+
+        for index in self.indexes.values_mut() {
+            index.remove(&old);
+        }
+
+        for index in self.ordered_indexes.values_mut() {
+            index.remove(&old);
+        }
+
+        for index in self.unique_constraints.values_mut() {
+            index.remove(&old);
+        }
+
+        self.references.remove(&old);
+
+        if let Some(search) = self.search.as_mut() {
+            search.remove(&old);
+        }
+
+        Some(old)
+    }
+
+    /// Looks up a named [OrderedIndex].
+    fn ordered_index(&self, name: &str) -> Result<&OrderedIndex<Record>, DatabaseError> {
+        self.ordered_indexes
+            .get(name)
+            .ok_or(DatabaseError::IndexNotFound)
+    }
+
+    /// Looks up the items with the given keys.
+    fn keys_to_items(&self, keys: Vec<&Record::Key>) -> Vec<&Record> {
+        keys.into_iter()
+            .filter_map(|key| self.items.get(key))
+            .collect()
+    }
+
+    /// Creates the indexes returned by [Keyed::indexes].
+    fn create_default_indexes(&mut self) {
+        for (name, extract) in Record::indexes() {
+            self.create_index(name, extract);
+        }
+    }
+
+    /// Encodes the database into a file in the given [Format].
+    fn encode(&self, format: Format) -> Result<Vec<u8>, DatabaseError> {
+        format.encode(
+            Self::schema_version(),
+            Record::type_name(),
+            self.to_value()?,
+        )
+    }
 
-        if definate_path.exists() {
-            std::fs::remove_file(&definate_path)?;
+    /// Converts the database into its [Value] form, as saved inside of a file.
+    ///
+    /// Uncommitted changes are never saved, so this fails with
+    /// [DatabaseError::TransactionOpen] while a transaction is open.
+    pub(crate) fn to_value(&self) -> Result<Value, DatabaseError> {
+        if self.in_transaction() {
+            return Err(DatabaseError::TransactionOpen);
         }
 
-        Ok(File::create(&definate_path)?)
+        Value::serialize_from(self)
+    }
+
+    /// Returns [Database::format], or the format inferred from the save path
+    /// if it isn't set.
+    fn smart_format_get(&self) -> Format {
+        self.format
+            .unwrap_or_else(|| Format::from_path(&self.smart_path_get()))
     }
 
     /// Automatically allocates a path for the database if [Database::save_path]
     /// is not provided. If it is, this function will simply return it.
-    fn smart_path_get(&self) -> PathBuf {
+    pub(crate) fn smart_path_get(&self) -> PathBuf {
         if self.save_path.is_none() {
             return PathBuf::from(format!("{}.gddb", self.label));
         }
@@ -384,8 +1263,79 @@ impl<Record: hash::Hash + Eq + Serialize + DeserializeOwned> Database<Record> {
     }
 }
 
+/// Atomically replaces the file at `path` with `stream`, first keeping up to
+/// `backups` previous versions as `[path].1`, `[path].2` and so on.
+pub(crate) fn write_atomic(
+    path: &Path,
+    stream: &[u8],
+    backups: usize,
+) -> Result<(), DatabaseError> {
+    let temp_path = suffixed_path(path, "tmp");
+
+    let written = File::create(&temp_path).and_then(|mut file| {
+        file.write_all(stream)?;
+        file.sync_all()
+    });
+
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+
+    if backups > 0 && path.exists() {
+        for n in (1..backups).rev() {
+            let older = backup_path(path, n);
+
+            if older.exists() {
+                std::fs::rename(&older, backup_path(path, n + 1))?;
+            }
+        }
+
+        std::fs::copy(path, backup_path(path, 1))?;
+    }
+
+    std::fs::rename(&temp_path, path)?;
+    sync_parent_dir(path);
+
+    Ok(())
+}
+
+/// Returns the path of the `n`th backup of a save path.
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    suffixed_path(path, &n.to_string())
+}
+
+/// Appends `.[suffix]` to the end of a path, keeping its existing extension.
+pub(crate) fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+
+    PathBuf::from(name)
+}
+
+/// Flushes a rename inside of the parent directory to disk. This isn't
+/// supported on every platform so is best-effort.
+fn sync_parent_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
 /// Reads a given path and converts it into a [Vec]<[u8]> stream.
-fn get_stream_from_path(path: PathBuf) -> Result<Vec<u8>, DatabaseError> {
+pub(crate) fn get_stream_from_path(path: PathBuf) -> Result<Vec<u8>, DatabaseError> {
     if !path.exists() {
         return Err(DatabaseError::DatabaseNotFound);
     }
@@ -434,11 +1384,10 @@ mod tests {
         db.create(testing_struct.clone())?;
 
         let mut updated_struct = testing_struct.clone();
-        updated_struct.attributes = "Testing".into();
+        updated_struct.set("name", "Testing");
         db.update(&testing_struct, updated_struct)?;
         let record = db.find(|f| &f.uuid, testing_struct.uuid)?;
-        let attributes: String = "Testing".into();
-        assert_eq!(attributes, record.attributes);
+        assert_eq!(record.get("name"), Some(&Value::from("Testing")));
         Ok(())
     }
 
@@ -518,6 +1467,258 @@ mod tests {
         Ok(())
     }
 
+    /// Tests [Database::from] returns an error rather than panicking when
+    /// given a file that isn't a database
+    #[test]
+    fn db_from_corrupt() {
+        std::fs::write("corrupt.gddb", b"not a database").unwrap();
+
+        let db: Result<Database<Record>, DatabaseError> = Database::from("corrupt.gddb");
+        assert!(matches!(db, Err(DatabaseError::CorruptFile)));
+
+        std::fs::remove_file("corrupt.gddb").unwrap();
+    }
+
+    /// Tests databases can be saved and loaded in every [Format], inferring
+    /// the format from the save path
+    #[test]
+    fn db_formats() -> Result<(), DatabaseError> {
+        let mut record = Record::new("Enemy".into());
+        record.set("hp", 10);
+
+        for format in [Format::Json, Format::MessagePack, Format::Ron] {
+            let path = PathBuf::from(format!("formats_test.{}", format.extension()));
+            let mut db = Database::new("Formats test", Some(path.clone()), false);
+            db.create(record.clone())?;
+            db.dump_db()?;
+
+            let loaded: Database<Record> = Database::from(path.clone())?;
+            assert_eq!(loaded.items, db.items);
+            assert_eq!(loaded.query_index("by_model", "Enemy")?.len(), 1);
+
+            std::fs::remove_file(path)?;
+        }
+
+        let path = PathBuf::from("formats_test.gddb");
+        let mut db = Database::new("Formats test", Some(path.clone()), false);
+        db.create(record)?;
+        db.dump_db_as(Format::Json)?;
+
+        let text = std::fs::read_to_string(&path)?;
+        assert!(text.contains("\"hp\": 10"));
+
+        let loaded: Database<Record> = Database::from_with_format(path.clone(), Format::Json)?;
+        assert_eq!(loaded.items, db.items);
+        assert_eq!(loaded.format, Some(Format::Json));
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    /// Tests `NaN` and infinities survive being saved in binary formats and
+    /// journaled
+    #[test]
+    fn db_non_finite_floats() -> Result<(), DatabaseError> {
+        let mut record = Record::new("Enemy".into());
+        record.set("nan", f64::NAN);
+        record.set("inf", f64::INFINITY);
+        let key = record.primary_key();
+
+        for format in [Format::Bincode, Format::MessagePack] {
+            let path = PathBuf::from(format!("floats_test.{}", format.extension()));
+            let mut db = Database::new("Floats test", Some(path.clone()), false);
+            db.create(record.clone())?;
+            db.dump_db()?;
+
+            db.journal = true;
+            let mut journaled = record.clone();
+            journaled.set("journaled", f64::NEG_INFINITY);
+            db.upsert(journaled)?;
+
+            let loaded: Database<Record> = Database::from(path.clone())?;
+            let item = loaded.get(&key)?;
+            assert!(item.get("nan").and_then(Value::as_f64).unwrap().is_nan());
+            assert_eq!(item.get("inf"), Some(&Value::Float(f64::INFINITY)));
+            assert_eq!(
+                item.get("journaled"),
+                Some(&Value::Float(f64::NEG_INFINITY))
+            );
+
+            db.compact()?;
+            std::fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Tests changes made in journal mode are replayed when loading, and
+    /// folded into the save by [Database::compact]
+    #[test]
+    fn db_journal() -> Result<(), DatabaseError> {
+        let path = PathBuf::from("journal_test.gddb");
+        let journal = PathBuf::from("journal_test.gddb.journal");
+        let mut db = Database::new("Journal test", Some(path.clone()), false);
+        db.journal = true;
+        db.dump_db()?;
+
+        let kept = Record::new("Testing".into());
+        let removed = Record::new("Testing".into());
+        let mut updated = kept.clone();
+        updated.set("hp", 10);
+
+        db.create(kept.clone())?;
+        db.create(removed.clone())?;
+        db.update(&kept, updated.clone())?;
+        db.destroy(&removed)?;
+        assert!(journal.exists());
+
+        let replayed: Database<Record> = Database::from(path.clone())?;
+        assert_eq!(replayed.items, db.items);
+        assert_eq!(replayed.query_index("by_model", "Testing")?.len(), 1);
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&journal)?;
+        file.write_all(&[16, 0, 0, 0, 1, 2])?;
+
+        let torn: Database<Record> = Database::from(path.clone())?;
+        assert_eq!(torn.items, db.items);
+
+        db.compact()?;
+        assert!(!journal.exists());
+
+        let compacted: Database<Record> = Database::from(path.clone())?;
+        assert_eq!(compacted.items, db.items);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    /// Tests a journal is replayed when nothing has been saved yet, and can't
+    /// be folded into the save inside of a transaction
+    #[test]
+    fn journal_without_save() -> Result<(), DatabaseError> {
+        let path = PathBuf::from("journal_only_test.gddb");
+        let journal = PathBuf::from("journal_only_test.gddb.journal");
+        let mut db = Database::new("journal_only_test", Some(path.clone()), false);
+        db.journal = true;
+
+        let record = Record::new("Testing".into());
+        db.create(record.clone())?;
+        assert!(!path.exists());
+
+        let mut replayed: Database<Record> = Database::auto_from(path.clone(), false)?;
+        assert_eq!(replayed.items, db.items);
+        assert!(replayed.journal);
+
+        replayed.begin();
+        replayed.create(Record::new("Testing".into()))?;
+        assert!(matches!(
+            replayed.dump_db(),
+            Err(DatabaseError::TransactionOpen)
+        ));
+        assert!(journal.exists());
+        replayed.commit()?;
+        replayed.dump_db()?;
+        assert!(!journal.exists());
+
+        let loaded: Database<Record> = Database::from(path.clone())?;
+        assert_eq!(loaded.items, replayed.items);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    /// A type with its own [Keyed::type_name], so its migrations don't affect
+    /// other tests
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Enemy {
+        id: i32,
+        hp: i64,
+    }
+
+    impl Keyed for Enemy {
+        type Key = i32;
+
+        fn primary_key(&self) -> i32 {
+            self.id
+        }
+
+        fn type_name() -> &'static str {
+            "MigrationTestEnemy"
+        }
+    }
+
+    /// Tests saves made with an older schema version are migrated when loaded,
+    /// and that saves from a newer schema version or another type are rejected
+    #[test]
+    fn db_migrations() -> Result<(), DatabaseError> {
+        let path = PathBuf::from("migrations_test.gddb");
+        let mut db: Database<Enemy> = Database::new("Migrations test", Some(path.clone()), false);
+
+        db.create(Enemy { id: 1, hp: 10 })?;
+        db.dump_db()?;
+
+        Database::<Enemy>::register_migration(0, |_, mut enemy| {
+            let hp = enemy.get("hp").and_then(Value::as_i64).unwrap_or(0);
+            enemy.insert("hp", hp * 2);
+            enemy
+        });
+        assert_eq!(Database::<Enemy>::schema_version(), 1);
+
+        let migrated: Database<Enemy> = Database::from(path.clone())?;
+        assert_eq!(migrated.get(&1)?, &Enemy { id: 1, hp: 20 });
+
+        migrated.dump_db()?;
+        let reloaded: Database<Enemy> = Database::from(path.clone())?;
+        assert_eq!(reloaded.get(&1)?, &Enemy { id: 1, hp: 20 });
+
+        let records: Result<Database<Record>, DatabaseError> = Database::from(path.clone());
+        assert!(matches!(records, Err(DatabaseError::TypeMismatch(_))));
+
+        let stream = std::fs::read(&path)?;
+        let (mut header, payload) = FileHeader::decode(&stream)?;
+        header.schema_version = 2;
+        std::fs::write(&path, header.encode(payload))?;
+
+        let newer: Result<Database<Enemy>, DatabaseError> = Database::from(path.clone());
+        assert!(matches!(newer, Err(DatabaseError::VersionMismatch(2))));
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    /// Tests [Database::dump_db] keeps numbered backups which can be restored
+    /// with [Database::restore_backup]
+    #[test]
+    fn db_backups() -> Result<(), DatabaseError> {
+        let path = PathBuf::from("backups_test.gddb");
+        let mut db = Database::new("Backups test", Some(path.clone()), false);
+        db.backups = 2;
+
+        let first = Record::new("Testing".into());
+        db.create(first.clone())?;
+        db.dump_db()?;
+
+        for _ in 0..3 {
+            db.create(Record::new("Testing".into()))?;
+            db.dump_db()?;
+        }
+
+        assert!(!suffixed_path(&path, "tmp").exists());
+        assert!(!backup_path(&path, 3).exists());
+
+        db.restore_backup(2)?;
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.query_index("by_model", "Testing")?.len(), 2);
+        assert!(db.contains(&first));
+        assert!(db.restore_backup(3).is_err());
+
+        for file in [path.clone(), backup_path(&path, 1), backup_path(&path, 2)] {
+            std::fs::remove_file(file)?;
+        }
+
+        Ok(())
+    }
+
     /// Test if the database contains that exact item, related to
     /// [Database::contains].
     #[test]
@@ -542,6 +1743,256 @@ mod tests {
         let _net_db: Database<Record> = Database::auto_from(new_db_path, false).unwrap();
     }
 
+    /// Tests [Database::get] and [Database::get_mut] looking up by uuid
+    #[test]
+    fn get_by_key() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Get test", None, true);
+
+        let record = Record::new("Testing".into());
+
+        db.create(record.clone())?;
+        db.create(Record::new("Testing".into()))?;
+
+        assert_eq!(db.get(&record.uuid)?, &record);
+        assert!(db.get("missing").is_err());
+
+        db.get_mut(&record.uuid)?.set("name", "Changed");
+        assert_eq!(
+            db.get(&record.uuid)?.get("name"),
+            Some(&Value::from("Changed"))
+        );
+
+        Ok(())
+    }
+
+    /// Tests [Database::modify] changes items in place, keeping indexes in
+    /// sync and skipping unchanged items
+    #[test]
+    fn modify_in_place() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Modify test", None, true);
+        db.history_depth = 10;
+
+        let record = Record::new("Enemy".into());
+        db.create(record.clone())?;
+
+        let previous = db.modify(&record.uuid, |enemy| {
+            enemy.model = "Boss".into();
+            enemy.set("hp", 100)
+        })?;
+        assert_eq!(previous, None);
+        assert_eq!(db.get(&record.uuid)?.get("hp"), Some(&Value::Int(100)));
+        assert_eq!(db.query_index("by_model", "Boss")?.len(), 1);
+        assert!(db.query_index("by_model", "Enemy").is_err());
+
+        db.modify(&record.uuid, |_| ())?;
+        db.undo()?;
+        assert_eq!(db.get(&record.uuid)?, &record);
+
+        assert!(matches!(
+            db.modify("missing", |_| ()),
+            Err(DatabaseError::ItemNotFound)
+        ));
+
+        Ok(())
+    }
+
+    /// Tests [Database::upsert] and [Database::remove_by_key]
+    #[test]
+    fn upsert_and_remove_by_key() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Upsert test", None, true);
+
+        let record = Record::new("Testing".into());
+        assert_eq!(db.upsert(record.clone())?, None);
+
+        let mut replacement = record.clone();
+        replacement.set("name", "Replaced");
+        assert_eq!(db.upsert(replacement.clone())?, Some(record.clone()));
+        assert_eq!(db.len(), 1);
+
+        assert_eq!(db.remove_by_key(&record.uuid)?, replacement);
+        assert!(db.remove_by_key(&record.uuid).is_err());
+
+        Ok(())
+    }
+
+    /// Tests [Database::destroy_by] and [Database::destroy_where] remove every
+    /// match
+    #[test]
+    fn destroy_matching() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Destroy test", None, true);
+
+        let player = Record::new("Player".into());
+        let mut boss = Record::new("Enemy".into());
+        boss.set("hp", 100);
+
+        db.create(player.clone())?;
+        db.create(boss.clone())?;
+        db.create(Record::new("Enemy".into()))?;
+        db.create(Record::new("Enemy".into()))?;
+
+        assert_eq!(db.destroy_by(|r| &r.uuid, player.uuid)?, 1);
+        assert_eq!(
+            db.destroy_where(|r| r.model == "Enemy" && r.get("hp").is_none())?,
+            2
+        );
+        assert_eq!(db.destroy_by(|r| &r.model, "Player".to_string())?, 0);
+        assert_eq!(db.items.values().collect::<Vec<_>>(), vec![&boss]);
+
+        Ok(())
+    }
+
+    /// Tests the built-in `by_model` index is kept in sync with changes
+    #[test]
+    fn query_model_index() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Index test", None, true);
+
+        let player = Record::new("Player".into());
+        db.create(player.clone())?;
+        db.create(Record::new("Player".into()))?;
+        db.create(Record::new("Enemy".into()))?;
+
+        assert_eq!(db.query_index("by_model", "Player")?.len(), 2);
+
+        let mut changed = player.clone();
+        changed.model = "Enemy".into();
+        db.update(&player, changed.clone())?;
+
+        assert_eq!(db.query_index("by_model", "Player")?.len(), 1);
+        assert_eq!(db.query_index("by_model", "Enemy")?.len(), 2);
+
+        db.destroy(&changed)?;
+        assert_eq!(db.query_index("by_model", "Enemy")?.len(), 1);
+        assert!(db.query_index("by_uuid", "Enemy").is_err());
+
+        Ok(())
+    }
+
+    /// Tests range, prefix, minimum and maximum lookups on ordered indexes
+    /// stay in sync with changes
+    #[test]
+    fn ordered_indexes() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Ordered index test", None, true);
+
+        for (name, level) in [("Slime", 1), ("Skeleton", 5), ("Dragon", 20), ("Spider", 5)] {
+            let mut enemy = Record::new("Enemy".into());
+            enemy.set("name", name);
+            enemy.set("level", level);
+            db.create(enemy)?;
+        }
+        db.create(Record::new("Enemy".into()))?;
+
+        db.create_path_index("by_level", "level");
+        db.create_ordered_index("by_name", |r: &Record| {
+            r.get("name").cloned().unwrap_or_default()
+        });
+
+        let names = |records: Vec<&Record>| -> Vec<String> {
+            let mut names: Vec<String> = records
+                .iter()
+                .map(|r| r.get("name").and_then(Value::as_str).unwrap().into())
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(
+            names(db.query_range("by_level", Value::from(2)..=Value::from(5))?),
+            vec!["Skeleton", "Spider"]
+        );
+        assert_eq!(
+            names(db.query_range("by_level", Value::from(5.5)..)?),
+            vec!["Dragon"]
+        );
+        assert!(db
+            .query_range("by_level", Value::from(5)..Value::from(1))?
+            .is_empty());
+        assert_eq!(
+            names(db.query_prefix("by_name", "S")?),
+            ["Skeleton", "Slime", "Spider"]
+        );
+        assert_eq!(names(db.index_min("by_level")?), vec!["Slime"]);
+
+        let dragon = db.index_max("by_level")?[0].clone();
+        db.destroy(&dragon)?;
+        assert_eq!(names(db.index_max("by_level")?), vec!["Skeleton", "Spider"]);
+
+        db.drop_index("by_level")?;
+        assert!(matches!(
+            db.query_range("by_level", ..),
+            Err(DatabaseError::IndexNotFound)
+        ));
+
+        Ok(())
+    }
+
+    /// Tests ordered indexes keep `NaN` and map values apart, and can still
+    /// remove them
+    #[test]
+    fn ordered_index_values() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Ordered values test", None, false);
+        db.create_path_index("by_stats", "stats");
+
+        let map = |hp: i64| {
+            let mut map = Value::map();
+            map.insert("hp", hp);
+            map
+        };
+        let mut stats = Vec::new();
+
+        for value in [
+            Value::Float(f64::NAN),
+            Value::Int(3),
+            Value::Int((1 << 53) + 1),
+            Value::Float((1u64 << 53) as f64),
+            map(1),
+            map(2),
+        ] {
+            let mut record = Record::new("Enemy".into());
+            record.set("stats", value);
+            db.create(record.clone())?;
+            stats.push(record);
+        }
+
+        let index = db.ordered_index("by_stats")?;
+        assert_eq!(index.range(Value::from(3)..=Value::from(3)).len(), 1);
+        assert_eq!(index.range(Value::from(4)..).len(), 5);
+        assert_eq!(index.same_value(&stats[4]), vec![&stats[4].uuid]);
+        assert_eq!(index.min(), vec![&stats[1].uuid]);
+        assert_eq!(index.max(), vec![&stats[5].uuid]);
+
+        for record in stats.iter() {
+            db.remove_by_key(&record.uuid)?;
+        }
+        assert!(db.ordered_index("by_stats")?.range(..).is_empty());
+
+        Ok(())
+    }
+
+    /// Tests [Database::create_index] indexes existing items
+    #[test]
+    fn create_index() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Custom index test", None, true);
+
+        let mut record = Record::new("Item".into());
+        record.set("name", "sword");
+        db.create(record)?;
+
+        db.create_index("by_attributes", |r: &Record| r.attributes.to_string());
+        db.create(Record::new("Item".into()))?;
+
+        assert_eq!(
+            db.query_index("by_attributes", r#"{"name":"sword"}"#)?
+                .len(),
+            1
+        );
+        assert_eq!(db.query_index("by_attributes", "{}")?.len(), 1);
+
+        db.drop_index("by_attributes")?;
+        assert!(db.query_index("by_attributes", "{}").is_err());
+
+        Ok(())
+    }
+
     /// Tests [Database::len] returns the number of database entries
     #[test]
     fn len() {
@@ -6,6 +6,10 @@ pub enum DatabaseError {
     /// When the item queried for was not found
     ItemNotFound,
 
+    /// When the named index queried for was not declared with
+    /// [crate::Database::create_index].
+    IndexNotFound,
+
     /// A duplicate value was found when adding to the database with
     /// [crate::Database::strict_dupes] allowed.
     DupeFound,
@@ -25,10 +29,161 @@ pub enum DatabaseError {
     /// [crate::Database::auto_from] does not have a valid file stem or could not
     /// convert from an [std::ffi::OsString] to a [String].
     BadDbName,
+
+    /// When a query string given to [crate::Query::parse] is invalid, with a
+    /// description of the problem.
+    BadQuery(String),
+
+    /// When a file loaded with [crate::Database::from] isn't a GDDB save or
+    /// doesn't match its checksum, typically because it was damaged.
+    CorruptFile,
+
+    /// When a save in a directory loaded with [crate::Catalog::from_dir] is
+    /// damaged like a [DatabaseError::CorruptFile], with the name of the file.
+    CorruptCollection(String),
+
+    /// When a file loaded with [crate::Database::from] was saved by a newer
+    /// version of GDDB or with a newer [crate::Database::schema_version], with
+    /// the version found in the file.
+    VersionMismatch(u32),
+
+    /// When a file loaded with [crate::Database::from] stores a different type
+    /// of item, with the [crate::Keyed::type_name] found in the file.
+    TypeMismatch(String),
+
+    /// When [crate::Database::commit] or [crate::Database::rollback] is called
+    /// without a transaction begun by [crate::Database::begin].
+    NoTransaction,
+
+    /// When saving with [crate::Database::dump_db] or stepping through the
+    /// history with [crate::Database::undo] while a transaction is open, as
+    /// its changes may still be rolled back.
+    TransactionOpen,
+
+    /// When there is nothing to undo or redo in the history, see
+    /// [crate::Database::undo].
+    NoHistory,
+
+    /// When the checkpoint given to [crate::Database::revert_to] was never
+    /// made or is no longer in the history.
+    CheckpointNotFound,
+
+    /// When an attribute isn't the type an operation needs, such as
+    /// incrementing a string, with the path of the attribute.
+    WrongType(String),
+
+    /// When a collection isn't in a [crate::Catalog], with the name of the
+    /// collection.
+    CollectionNotFound(String),
+
+    /// When an item doesn't match the [crate::Schema] of its model, with the
+    /// field that failed and why.
+    ValidationFailed { field: String, reason: String },
+
+    /// When an item shares a value with another item under a unique
+    /// constraint, with the name of the constraint. See
+    /// [crate::Database::create_unique].
+    UniqueViolation(String),
+
+    /// When an item can't be destroyed because others refer to it through a
+    /// relation with [crate::OnDestroy::Restrict], with the name of the
+    /// relation.
+    RelationRestricted(String),
+
+    /// When an item refers through a relation to an item that doesn't exist,
+    /// or isn't what the relation expects such as a record of another model,
+    /// with the name of the relation.
+    BrokenReference(String),
 }
 
+impl std::fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseError::ItemNotFound => write!(f, "Item not found"),
+            DatabaseError::IndexNotFound => write!(f, "Index not found"),
+            DatabaseError::DupeFound => write!(f, "Duplicate item found"),
+            DatabaseError::SavePathRequired => write!(f, "A save path is required"),
+            DatabaseError::IOError(e) => write!(f, "IO error: {}", e),
+            DatabaseError::DatabaseNotFound => write!(f, "Database file not found"),
+            DatabaseError::BadDbName => write!(f, "Invalid database name"),
+            DatabaseError::BadQuery(reason) => write!(f, "Invalid query: {}", reason),
+            DatabaseError::CorruptFile => write!(f, "Database file is corrupt"),
+            DatabaseError::CorruptCollection(file) => {
+                write!(f, "Collection file {} is corrupt", file)
+            }
+            DatabaseError::VersionMismatch(version) => {
+                write!(f, "Database file version {} is not supported", version)
+            }
+            DatabaseError::TypeMismatch(name) => {
+                write!(f, "Database file stores {} items", name)
+            }
+            DatabaseError::NoTransaction => write!(f, "No transaction has begun"),
+            DatabaseError::TransactionOpen => write!(f, "A transaction is still open"),
+            DatabaseError::NoHistory => write!(f, "Nothing to undo or redo"),
+            DatabaseError::CheckpointNotFound => write!(f, "Checkpoint not found"),
+            DatabaseError::WrongType(path) => {
+                write!(f, "Attribute {} has the wrong type", path)
+            }
+            DatabaseError::CollectionNotFound(name) => {
+                write!(f, "Collection {} not found", name)
+            }
+            DatabaseError::ValidationFailed { field, reason } => {
+                write!(f, "Field {} {}", field, reason)
+            }
+            DatabaseError::UniqueViolation(name) => {
+                write!(f, "Unique constraint {} violated", name)
+            }
+            DatabaseError::RelationRestricted(relation) => {
+                write!(f, "Records still refer to this through {}", relation)
+            }
+            DatabaseError::BrokenReference(relation) => {
+                write!(f, "Reference through {} points at the wrong item", relation)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
 impl From<std::io::Error> for DatabaseError {
     fn from(e: std::io::Error) -> Self {
         DatabaseError::IOError(e)
     }
 }
+
+/// Serialization faliures are reported as an [DatabaseError::IOError], using
+/// [std::io::ErrorKind::InvalidData] unless they were caused by IO.
+impl From<bincode::Error> for DatabaseError {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            bincode::ErrorKind::Io(e) => DatabaseError::IOError(e),
+            e => DatabaseError::IOError(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        }
+    }
+}
+
+/// Conversion faliures are reported as an [DatabaseError::IOError], using
+/// [std::io::ErrorKind::InvalidData] unless they were caused by IO.
+impl From<serde_json::Error> for DatabaseError {
+    fn from(e: serde_json::Error) -> Self {
+        DatabaseError::IOError(e.into())
+    }
+}
+
+impl From<crate::convert::ConvertError> for DatabaseError {
+    fn from(e: crate::convert::ConvertError) -> Self {
+        DatabaseError::IOError(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl From<rmp_serde::encode::Error> for DatabaseError {
+    fn from(e: rmp_serde::encode::Error) -> Self {
+        DatabaseError::IOError(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl From<ron::Error> for DatabaseError {
+    fn from(e: ron::Error) -> Self {
+        DatabaseError::IOError(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
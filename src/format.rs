@@ -0,0 +1,222 @@
+//! Serialization formats a [crate::Database] can be saved in.
+
+use crate::prelude::*;
+use std::path::Path;
+
+/// The format of a saved database, see [crate::Database::format].
+///
+/// Binary formats start with a [FileHeader] and are checksummed. Text formats
+/// instead store the header fields under a `gddb` key next to the `database`
+/// itself, and aren't checksummed so they can be edited by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Format {
+    /// Compact binary format using [bincode], used for `.gddb` files.
+    #[default]
+    Bincode,
+
+    /// Human-readable JSON, used for `.json` files.
+    Json,
+
+    /// Compact binary format using MessagePack, used for `.msgpack` files.
+    MessagePack,
+
+    /// Human-readable [RON](https://github.com/ron-rs/ron), used for `.ron`
+    /// files.
+    Ron,
+}
+
+impl Format {
+    /// Infers the format from the extension of a path, falling back to
+    /// [Format::Bincode] for `.gddb` and any other extension.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Format::Json,
+            Some("msgpack") => Format::MessagePack,
+            Some("ron") => Format::Ron,
+            _ => Format::Bincode,
+        }
+    }
+
+    /// The file extension used for this format, without a leading `.`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Bincode => "gddb",
+            Format::Json => "json",
+            Format::MessagePack => "msgpack",
+            Format::Ron => "ron",
+        }
+    }
+
+    /// If this format is a text format that can be read and edited by hand.
+    pub fn is_human_readable(&self) -> bool {
+        matches!(self, Format::Json | Format::Ron)
+    }
+
+    /// Encodes a database, given in its [Value] form, into a file.
+    pub fn encode(
+        &self,
+        schema_version: u32,
+        type_name: &str,
+        database: Value,
+    ) -> Result<Vec<u8>, DatabaseError> {
+        if !self.is_human_readable() {
+            let payload = match self {
+                Format::MessagePack => rmp_serde::to_vec(&database)?,
+                _ => bincode::serialize(&database)?,
+            };
+            let header = FileHeader::new(schema_version, type_name, &payload);
+
+            return Ok(header.encode(&payload));
+        }
+
+        let mut header = Value::map();
+        header.insert("format_version", FORMAT_VERSION as i64);
+        header.insert("schema_version", schema_version as i64);
+        header.insert("type_name", type_name);
+
+        let mut document = Value::map();
+        document.insert("gddb", header);
+        document.insert("database", database);
+
+        let stream = match self {
+            Format::Json => serde_json::to_vec_pretty(&document)?,
+            _ => ron::ser::to_string_pretty(&document, Default::default())?.into_bytes(),
+        };
+
+        Ok(stream)
+    }
+
+    /// Decodes a file made by [Format::encode], returning its header and the
+    /// database in its [Value] form. The header of a text format has a
+    /// `checksum` of `0`.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::CorruptFile] if the file isn't valid for
+    /// this format and [DatabaseError::VersionMismatch] if it was written by
+    /// a newer version of GDDB.
+    pub fn decode(&self, stream: &[u8]) -> Result<(FileHeader, Value), DatabaseError> {
+        if !self.is_human_readable() {
+            let (header, payload) = FileHeader::decode(stream)?;
+            let database = match self {
+                Format::MessagePack => rmp_serde::from_slice(payload).ok(),
+                _ => bincode::deserialize(payload).ok(),
+            };
+
+            return Ok((header, database.ok_or(DatabaseError::CorruptFile)?));
+        }
+
+        let mut document = match self.parse_document(stream) {
+            Some(Value::Map(document)) => document,
+            _ => return Err(DatabaseError::CorruptFile),
+        };
+        let header = document.remove("gddb").ok_or(DatabaseError::CorruptFile)?;
+        let database = document
+            .remove("database")
+            .ok_or(DatabaseError::CorruptFile)?;
+
+        let version = |key| {
+            header
+                .get(key)
+                .and_then(Value::as_i64)
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or(DatabaseError::CorruptFile)
+        };
+
+        let format_version = version("format_version")?;
+
+        if format_version > FORMAT_VERSION {
+            return Err(DatabaseError::VersionMismatch(format_version));
+        }
+
+        let header = FileHeader {
+            format_version,
+            schema_version: version("schema_version")?,
+            type_name: header
+                .get("type_name")
+                .and_then(Value::as_str)
+                .ok_or(DatabaseError::CorruptFile)?
+                .to_string(),
+            checksum: 0,
+        };
+
+        Ok((header, database))
+    }
+
+    /// If a file could be a save in this format, whether or not it's damaged.
+    /// Binary saves start with the [MAGIC] bytes and text saves are a
+    /// document with a `gddb` key, while text that can't be parsed at all may
+    /// be a damaged save.
+    pub(crate) fn is_save(&self, stream: &[u8]) -> bool {
+        if !self.is_human_readable() {
+            return stream.starts_with(MAGIC);
+        }
+
+        match self.parse_document(stream) {
+            Some(Value::Map(document)) => document.contains_key("gddb"),
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    /// Parses the document of a text format, holding the header and database.
+    fn parse_document(&self, stream: &[u8]) -> Option<Value> {
+        match self {
+            Format::Json => serde_json::from_slice(stream).ok(),
+            _ => std::str::from_utf8(stream)
+                .ok()
+                .and_then(|text| ron::from_str(text).ok()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests every format can decode what it encodes, and rejects files from
+    /// other formats
+    #[test]
+    fn format_round_trip() -> Result<(), DatabaseError> {
+        let database = Value::from(serde_json::json!({
+            "label": "Formats",
+            "save_path": null,
+            "items": { "a": { "hp": 10, "speed": 1.5, "tags": ["boss"] } },
+        }));
+
+        for format in [
+            Format::Bincode,
+            Format::Json,
+            Format::MessagePack,
+            Format::Ron,
+        ] {
+            let stream = format.encode(2, "Record", database.clone())?;
+            let (header, decoded) = format.decode(&stream)?;
+
+            assert_eq!(header.schema_version, 2);
+            assert_eq!(header.type_name, "Record");
+            assert_eq!(decoded, database);
+        }
+
+        let json = Format::Json.encode(0, "Record", database)?;
+        assert!(matches!(
+            Format::Bincode.decode(&json),
+            Err(DatabaseError::CorruptFile)
+        ));
+        assert!(matches!(
+            Format::Ron.decode(b"{}"),
+            Err(DatabaseError::CorruptFile)
+        ));
+
+        Ok(())
+    }
+
+    /// Tests formats are inferred from file extensions
+    #[test]
+    fn format_from_path() {
+        assert_eq!(Format::from_path(Path::new("save.json")), Format::Json);
+        assert_eq!(Format::from_path(Path::new("save.ron")), Format::Ron);
+        assert_eq!(Format::from_path(Path::new("save.gddb")), Format::Bincode);
+        assert_eq!(Format::from_path(Path::new("save")), Format::Bincode);
+    }
+}
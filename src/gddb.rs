@@ -1,95 +1,1375 @@
 use crate::prelude::*;
+use gdnative::api::ProjectSettings;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
 
 /// The primary Godot interface to the database.
+///
+/// Failed operations never panic. Methods returning a value return `null`
+/// instead, methods without a value return a Godot error code, and the reason
+/// is available from `last_error()` and emitted through the `error` signal.
+///
+/// Changes to records are emitted through the `record_created`,
+/// `record_updated` and `record_destroyed` signals once the call making them
+/// returns, deferred until idle time so handlers can call back into the
+/// database.
 #[derive(NativeClass)]
 #[inherit(Node)]
+#[register_with(Self::register_signals)]
 pub struct GDDB {
     storage: Database<Record>,
+    last_error: String,
+    changes: Arc<Mutex<Vec<Change<Record>>>>,
+    live_queries: Vec<(i64, Subscription)>,
+    path_indexes: HashMap<String, String>,
+    unique_paths: HashMap<String, (String, String)>,
+    collections: Catalog<Record>,
 }
 
 #[methods]
 impl GDDB {
     fn new(_owner: &Node) -> Self {
-        let db: Database<Record> = Database::new("GAME", None, false);
-        Self { storage: db }
+        let mut db: Database<Record> = Database::new("GAME", None, false);
+        let changes = Arc::new(Mutex::new(Vec::new()));
+
+        watch(&mut db, &changes);
+        Self {
+            storage: db,
+            last_error: String::new(),
+            changes,
+            live_queries: Vec::new(),
+            path_indexes: HashMap::new(),
+            unique_paths: HashMap::new(),
+            collections: Catalog::new(),
+        }
     }
 
-    // Creates a database record
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder
+            .signal("error")
+            .with_param("message", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("record_created")
+            .with_param("uuid", VariantType::GodotString)
+            .with_param("model", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("record_updated")
+            .with_param("uuid", VariantType::GodotString)
+            .with_param("model", VariantType::GodotString)
+            .with_param("old", VariantType::Dictionary)
+            .with_param("new", VariantType::Dictionary)
+            .done();
+
+        builder
+            .signal("record_destroyed")
+            .with_param("uuid", VariantType::GodotString)
+            .with_param("model", VariantType::GodotString)
+            .done();
+    }
+
+    // Creates a database record, returning its uuid
     #[export]
-    pub fn create(&mut self, _owner: &Node, model: String, attributes: Dictionary) -> String {
+    pub fn create(
+        &mut self,
+        owner: &Node,
+        model: String,
+        attributes: Dictionary,
+    ) -> Option<String> {
         let mut record = Record::new(model);
         let uuid = record.uuid.clone();
-        record.attributes = attributes.to_json().to_string();
-
-        self.storage.create(record).unwrap();
+        record.attributes = value_from_dictionary(&attributes);
 
-        uuid
+        let result = self.storage.create(record).map(|_| uuid);
+        self.check(owner, result)
     }
 
     // Finds a database record given a uuid
     #[export]
-    pub fn find(&mut self, _owner: &Node, uuid: String) -> GodotString {
-        let record = self
+    pub fn find(&mut self, owner: &Node, uuid: String) -> Option<Variant> {
+        let result = self.storage.get(&uuid).map(|record| record.to_variant());
+        self.check(owner, result)
+    }
+
+    // Updates a record, keeping its links
+    #[export]
+    pub fn update(
+        &mut self,
+        owner: &Node,
+        uuid: String,
+        model: String,
+        attributes: Dictionary,
+    ) -> i64 {
+        let result = self.storage.get(&uuid).cloned().and_then(|original| {
+            let new = Record {
+                model,
+                attributes: value_from_dictionary(&attributes),
+                ..original.clone()
+            };
+
+            self.storage.update(&original, new)
+        });
+
+        self.check_code(owner, result)
+    }
+
+    // Deep merges the keys of a dictionary into the attributes of a record,
+    // leaving any other attributes as they are. Returns a Godot error code, `OK`
+    // on success
+    #[export]
+    pub fn patch(&mut self, owner: &Node, uuid: String, attributes: Dictionary) -> i64 {
+        let patch = value_from_dictionary(&attributes);
+        let result = self
             .storage
-            .find(|f| &f.uuid, uuid)
-            .expect("Could not find record");
+            .modify(&uuid, |record| record.attributes.merge(patch));
 
-        let data = Dictionary::new();
+        self.check_code(owner, result)
+    }
 
-        data.insert("uuid", record.uuid.clone());
-        data.insert("model", record.model.clone());
-        data.insert("attributes", record.attributes.clone());
+    // Removes an attribute from a record by a `.` separated path such as
+    // `inventory.0`. Returns a Godot error code, `OK` on success
+    #[export]
+    pub fn unset(&mut self, owner: &Node, uuid: String, key_path: String) -> i64 {
+        let result = self.storage.modify(&uuid, |record| {
+            record.attributes.remove_path(&key_path);
+        });
 
-        data.to_json()
+        self.check_code(owner, result)
     }
 
-    // Updates a record
+    // Adds an amount to a numeric attribute, where a missing attribute counts
+    // as `0`. Returns the new value
     #[export]
-    pub fn update(&mut self, _owner: &Node, uuid: String, model: String, attributes: String) {
-        let new = Record {
-            uuid,
-            model,
-            attributes,
-        };
+    pub fn increment(
+        &mut self,
+        owner: &Node,
+        uuid: String,
+        path: String,
+        amount: Variant,
+    ) -> Option<Variant> {
+        let result = self
+            .storage
+            .increment(&uuid, &path, value_from_variant(&amount));
+        self.check(owner, result).map(|value| value.to_variant())
+    }
 
-        let uuid = new.uuid.clone();
-        let original = self
+    // Subtracts an amount from a numeric attribute. Returns the new value
+    #[export]
+    pub fn decrement(
+        &mut self,
+        owner: &Node,
+        uuid: String,
+        path: String,
+        amount: Variant,
+    ) -> Option<Variant> {
+        let result = self
+            .storage
+            .decrement(&uuid, &path, value_from_variant(&amount));
+        self.check(owner, result).map(|value| value.to_variant())
+    }
+
+    // Lowers a numeric attribute to a value if it's above it. Returns the new
+    // value
+    #[export]
+    pub fn min(
+        &mut self,
+        owner: &Node,
+        uuid: String,
+        path: String,
+        value: Variant,
+    ) -> Option<Variant> {
+        let result = self.storage.min(&uuid, &path, value_from_variant(&value));
+        self.check(owner, result).map(|value| value.to_variant())
+    }
+
+    // Raises a numeric attribute to a value if it's below it. Returns the new
+    // value
+    #[export]
+    pub fn max(
+        &mut self,
+        owner: &Node,
+        uuid: String,
+        path: String,
+        value: Variant,
+    ) -> Option<Variant> {
+        let result = self.storage.max(&uuid, &path, value_from_variant(&value));
+        self.check(owner, result).map(|value| value.to_variant())
+    }
+
+    // Appends a value to an array attribute, where a missing attribute counts
+    // as an empty array. Returns the new array
+    #[export]
+    pub fn append_to_array(
+        &mut self,
+        owner: &Node,
+        uuid: String,
+        path: String,
+        value: Variant,
+    ) -> Option<Variant> {
+        let result = self
             .storage
-            .find(|f| &f.uuid, uuid)
-            .expect("Could not find record to update")
-            .clone();
+            .append_to_array(&uuid, &path, value_from_variant(&value));
+        self.check(owner, result).map(|value| value.to_variant())
+    }
+
+    // Removes every copy of a value from an array attribute. Returns the new
+    // array
+    #[export]
+    pub fn remove_from_array(
+        &mut self,
+        owner: &Node,
+        uuid: String,
+        path: String,
+        value: Variant,
+    ) -> Option<Variant> {
+        let result = self
+            .storage
+            .remove_from_array(&uuid, &path, value_from_variant(&value));
+        self.check(owner, result).map(|value| value.to_variant())
+    }
+
+    // Removes a record by its uuid. Returns a Godot error code, `OK` on
+    // success
+    #[export]
+    pub fn destroy(&mut self, owner: &Node, uuid: String) -> i64 {
+        let result = self.storage.remove_by_key(&uuid).map(|_| ());
+        self.check_code(owner, result)
+    }
+
+    // Removes every record matching a query string, see `where`. Returns the
+    // number of records removed
+    #[export]
+    pub fn destroy_where(&mut self, owner: &Node, query: String) -> Option<i64> {
+        let result = Query::parse(&query).and_then(|query| {
+            self.storage
+                .destroy_where(|record| query.matches(record))
+                .map(|count| count as i64)
+        });
+
+        self.check(owner, result)
+    }
 
+    #[export]
+    pub fn all(&self, _owner: &Node) -> Vec<Variant> {
         self.storage
-            .update(&original, new.clone())
-            .expect("Cannot update record");
+            .items
+            .values()
+            .map(|record| record.to_variant())
+            .collect()
+    }
+
+    // Finds all records matching a query string, such as
+    // `model == "Enemy" and attributes.hp < 10`
+    #[export(name = "where")]
+    pub fn where_query(&mut self, owner: &Node, query: String) -> Vec<Variant> {
+        let result = Query::parse(&query).map(|query| {
+            self.storage
+                .filter(&query)
+                .into_iter()
+                .map(|record| record.to_variant())
+                .collect()
+        });
+
+        self.check(owner, result).unwrap_or_default()
+    }
+
+    // Finds all records matching a query string, or every record if it's
+    // empty, in a stable order. The options dictionary may contain:
+    //
+    // - `order_by`, paths to sort by such as `"score desc, name"`
+    // - `limit`, the most records to return
+    // - `offset`, the number of records to skip
+    // - `after`, a cursor returned by `select_page` to continue from
+    #[export]
+    pub fn select(&mut self, owner: &Node, query: String, options: Dictionary) -> Vec<Variant> {
+        let result = select_options(&self.storage, &query, &options).map(|select| {
+            select
+                .records()
+                .iter()
+                .map(|record| record.to_variant())
+                .collect()
+        });
+
+        self.check(owner, result).unwrap_or_default()
+    }
+
+    // Like `select`, returning a `{ records, next }` dictionary where `next` is
+    // a cursor to pass as the `after` option for the next page, or `null` on
+    // the last page
+    #[export]
+    pub fn select_page(
+        &mut self,
+        owner: &Node,
+        query: String,
+        options: Dictionary,
+    ) -> Option<Dictionary> {
+        let result = select_options(&self.storage, &query, &options).map(|select| {
+            let page = select.page();
+            let data = Dictionary::new();
+
+            data.insert(
+                "records",
+                page.records
+                    .iter()
+                    .map(|record| record.to_variant())
+                    .collect::<VariantArray<Unique>>(),
+            );
+            data.insert("next", page.next.map(|cursor| cursor.encode()));
+            data.into_shared()
+        });
+
+        self.check(owner, result)
+    }
+
+    // Declares an ordered index over an attribute path such as `level`, for
+    // use with `query_range`, `query_prefix`, `index_min` and `index_max`. The
+    // index is kept across `load` and `open_or_create`
+    #[export]
+    pub fn create_path_index(&mut self, _owner: &Node, name: String, path: String) {
+        self.storage.create_path_index(name.clone(), path.clone());
+        self.path_indexes.insert(name, path);
+    }
+
+    // Declares a unique constraint, so no two records may share the value at
+    // an attribute path such as `username`. If `model` isn't empty, only
+    // records of that model are constrained. Records that would break it fail
+    // with `ERR_ALREADY_EXISTS`. The constraint is kept across `load` and
+    // `open_or_create`. Returns a Godot error code, `OK` on success
+    #[export]
+    pub fn create_unique(
+        &mut self,
+        owner: &Node,
+        name: String,
+        path: String,
+        model: String,
+    ) -> i64 {
+        let scope = Some(model.as_str()).filter(|model| !model.is_empty());
+        let result = self
+            .storage
+            .create_unique_path(name.clone(), path.clone(), scope);
+
+        if result.is_ok() {
+            self.unique_paths.insert(name, (path, model));
+        }
+        self.check_code(owner, result)
     }
 
-    // Removes a record
+    // Removes a unique constraint. Returns a Godot error code, `OK` on success
     #[export]
-    pub fn destroy(&mut self, _owner: &Node, uuid: String, model: String, attributes: String) {
-        let record = Record {
-            uuid,
-            model,
-            attributes,
+    pub fn drop_unique(&mut self, owner: &Node, name: String) -> i64 {
+        let declared = self.unique_paths.remove(&name).is_some();
+
+        // Constraints broken by loaded records are declared but not enforced
+        let result = match self.storage.drop_unique(&name) {
+            Err(DatabaseError::IndexNotFound) if declared => Ok(()),
+            result => result,
         };
+        self.check_code(owner, result)
+    }
 
-        self.storage.destroy(&record).expect("Cannot remove record");
+    // Makes a record refer to another through a relation such as `owner`,
+    // replacing any record it referred to before. Returns a Godot error code,
+    // `OK` on success
+    #[export]
+    pub fn link(&mut self, owner: &Node, uuid: String, relation: String, target: String) -> i64 {
+        let result = self.storage.link(&uuid, &relation, &target);
+        self.check_code(owner, result)
     }
 
+    // Removes the reference of a record through a relation. Returns a Godot
+    // error code, `OK` on success
     #[export]
-    pub fn all(&self, _owner: &Node) -> Vec<GodotString> {
-        let mut records = vec![];
+    pub fn unlink(&mut self, owner: &Node, uuid: String, relation: String) -> i64 {
+        let result = self.storage.unlink(&uuid, &relation).map(|_| ());
+        self.check_code(owner, result)
+    }
 
-        for record in self.storage.items.iter() {
-            let data = Dictionary::new();
+    // Finds the record a record refers to through a relation
+    #[export]
+    pub fn related(&mut self, owner: &Node, uuid: String, relation: String) -> Option<Variant> {
+        let result = self
+            .storage
+            .related(&uuid, &relation)
+            .map(|record| record.to_variant());
+        self.check(owner, result)
+    }
+
+    // Finds every record referring to a record through a relation
+    #[export]
+    pub fn referrers(&mut self, _owner: &Node, uuid: String, relation: String) -> Vec<Variant> {
+        records_to_variants(self.storage.referrers(&uuid, &relation))
+    }
+
+    // Sets what happens to the records referring to a record through a
+    // relation when it's destroyed: `cascade` destroys them too, `nullify`
+    // removes their references and `restrict`, the default, fails with
+    // `ERR_ALREADY_IN_USE`. The rule is kept across `load` and
+    // `open_or_create`. Returns a Godot error code, `OK` on success
+    #[export]
+    pub fn set_on_destroy(&mut self, owner: &Node, relation: String, rule: String) -> i64 {
+        let result = OnDestroy::parse(&rule).map(|rule| self.storage.on_destroy(relation, rule));
+        self.check_code(owner, result)
+    }
+
+    // Finds all records with a value between `from` and `to` inclusive in an
+    // ordered index, smallest first. Either may be `null` to leave that end
+    // open
+    #[export]
+    pub fn query_range(
+        &mut self,
+        owner: &Node,
+        name: String,
+        from: Variant,
+        to: Variant,
+    ) -> Vec<Variant> {
+        let bound = |value: &Variant| {
+            if value.is_nil() {
+                Bound::Unbounded
+            } else {
+                Bound::Included(value_from_variant(value))
+            }
+        };
+
+        let result = self
+            .storage
+            .query_range(&name, (bound(&from), bound(&to)))
+            .map(records_to_variants);
+        self.check(owner, result).unwrap_or_default()
+    }
 
-            data.insert("uuid", record.uuid.clone());
-            data.insert("model", record.model.clone());
-            data.insert("attributes", record.attributes.clone());
+    // Finds all records with a string value starting with a prefix in an
+    // ordered index, in order
+    #[export]
+    pub fn query_prefix(&mut self, owner: &Node, name: String, prefix: String) -> Vec<Variant> {
+        let result = self
+            .storage
+            .query_prefix(&name, &prefix)
+            .map(records_to_variants);
+        self.check(owner, result).unwrap_or_default()
+    }
+
+    // Finds every record sharing the smallest value in an ordered index
+    #[export]
+    pub fn index_min(&mut self, owner: &Node, name: String) -> Vec<Variant> {
+        let result = self.storage.index_min(&name).map(records_to_variants);
+        self.check(owner, result).unwrap_or_default()
+    }
+
+    // Finds every record sharing the largest value in an ordered index
+    #[export]
+    pub fn index_max(&mut self, owner: &Node, name: String) -> Vec<Variant> {
+        let result = self.storage.index_max(&name).map(records_to_variants);
+        self.check(owner, result).unwrap_or_default()
+    }
+
+    // Finds every record with string attributes matching all words of `text`,
+    // most relevant first. Words match case-insensitively and by prefix, so
+    // `fir` finds `Fireball`. If `model_filter` isn't empty, only records of
+    // that model are returned.
+    //
+    // Search is enabled the first time this is called and kept across `load`
+    // and `open_or_create`
+    #[export]
+    pub fn search(&mut self, owner: &Node, text: String, model_filter: String) -> Vec<Variant> {
+        if !self.storage.search_enabled() {
+            self.storage.enable_search();
+        }
+
+        let result = self.storage.search(&text).map(|records| {
+            records
+                .into_iter()
+                .filter(|record| model_filter.is_empty() || record.model == model_filter)
+                .map(|record| record.to_variant())
+                .collect()
+        });
+        self.check(owner, result).unwrap_or_default()
+    }
+
+    // Computes aggregates over the records matching a query string, or every
+    // record if it's empty. The spec dictionary maps names to aggregates such
+    // as `"count"`, `"sum(gold)"`, `"avg(hp)"`, `"min(level)"` or
+    // `"max(level)"`, which are returned under the same names.
+    //
+    // If the spec has a `group_by` path, records are grouped by the value at
+    // that path and a dictionary of results is returned for each group, keyed
+    // by that value. Records missing the value are grouped under `null`
+    #[export]
+    pub fn aggregate(
+        &mut self,
+        owner: &Node,
+        query: String,
+        spec: Dictionary,
+    ) -> Option<Dictionary> {
+        let result = aggregate_spec(&self.storage, &query, &spec);
+        self.check(owner, result)
+    }
+
+    // Starts a live query from a query string, returning a `LiveQuery` whose
+    // `changed` signal is emitted whenever its results change
+    #[export]
+    pub fn live_query(
+        &mut self,
+        owner: &Node,
+        query: String,
+    ) -> Option<Instance<LiveQuery, Shared>> {
+        let result = self.storage.subscribe_where(&query);
+        let subscription = self.check(owner, result)?;
+        let live = Instance::emplace(LiveQuery {
+            subscription: subscription.clone(),
+        });
+
+        self.live_queries
+            .push((live.base().get_instance_id(), subscription));
+        Some(live.into_shared())
+    }
+
+    // Registers the schema records of a model are checked against when they're
+    // created or changed, mapping attribute paths to their rules, such as
+    // `{ "hp": { "type": "int", "required": true, "min": 0 } }`. Rules may
+    // have a `type`, `required`, `default`, `min`, `max` and `enum`. The
+    // schema is kept across `load` and `open_or_create`. Returns a Godot error
+    // code, `OK` on success
+    #[export]
+    pub fn define_model(&mut self, owner: &Node, name: String, schema: Dictionary) -> i64 {
+        let result = Schema::from_value(value_from_dictionary(&schema))
+            .map(|schema| self.storage.define_model(name, schema));
+        self.check_code(owner, result)
+    }
+
+    // Registers the schema of every model in a JSON file mapping model names
+    // to schemas, see `define_model`. Returns a Godot error code, `OK` on
+    // success
+    #[export]
+    pub fn load_models(&mut self, owner: &Node, path: String) -> i64 {
+        let result = self.storage.load_models(globalize_path(&path));
+        self.check_code(owner, result)
+    }
+
+    // Returns a `Collection` for a named collection of records, kept apart from
+    // the main database and every other collection. The collection is created
+    // if it doesn't exist yet, returning `null` if the name isn't valid
+    #[export]
+    pub fn collection(
+        &mut self,
+        owner: &Node,
+        name: String,
+    ) -> Option<Instance<Collection, Shared>> {
+        let result = self.collections.collection_or_create(name.clone(), false);
+        self.check(owner, result.map(|_| ()))?;
+
+        let collection = Collection {
+            gddb: owner.get_instance_id(),
+            name,
+        };
+        Some(Instance::emplace(collection).into_shared())
+    }
+
+    // The names of every collection, in order
+    #[export]
+    pub fn collection_names(&self, _owner: &Node) -> Vec<String> {
+        self.collections.names().map(String::from).collect()
+    }
+
+    // Removes a collection and all of its records. Returns a Godot error code,
+    // `OK` on success
+    #[export]
+    pub fn drop_collection(&mut self, owner: &Node, name: String) -> i64 {
+        let result = self.collections.drop_collection(&name).map(|_| ());
+        self.check_code(owner, result)
+    }
+
+    // Saves every collection to a single file, or to a file for each
+    // collection inside of a directory if the path has no extension. Returns a
+    // Godot error code, `OK` on success
+    #[export]
+    pub fn save_collections(&mut self, owner: &Node, path: String) -> i64 {
+        let path = globalize_path(&path);
+
+        let result = match path.extension() {
+            Some(_) => self.collections.dump_file(path),
+            None => self.collections.dump_dir(path),
+        };
+        self.check_code(owner, result)
+    }
+
+    // Replaces every collection with those saved by `save_collections` to a
+    // file or directory, leaving them unchanged on failure. Returns a Godot
+    // error code, `OK` on success
+    #[export]
+    pub fn load_collections(&mut self, owner: &Node, path: String) -> i64 {
+        let path = globalize_path(&path);
+
+        let result = if path.is_dir() {
+            Catalog::from_dir(path)
+        } else {
+            Catalog::from_file(path)
+        };
+        let result = result.map(|collections| self.collections = collections);
+        self.check_code(owner, result)
+    }
+
+    // Saves the database to a path, which may be a `user://` or `res://` path.
+    // Returns a Godot error code, `OK` on success
+    #[export]
+    pub fn save(&mut self, owner: &Node, path: String) -> i64 {
+        let previous = self.storage.save_path.replace(globalize_path(&path));
 
-            records.push(data.to_json());
+        let result = self.storage.dump_db();
+        if result.is_err() {
+            self.storage.save_path = previous;
         }
+        self.check_code(owner, result)
+    }
+
+    // Begins a transaction, grouping the following changes until `commit` or
+    // `rollback` is called. Transactions can be nested
+    #[export]
+    pub fn begin_transaction(&mut self, _owner: &Node) {
+        self.storage.begin();
+    }
+
+    // Commits the innermost transaction. Returns a Godot error code, `OK` on
+    // success
+    #[export]
+    pub fn commit(&mut self, owner: &Node) -> i64 {
+        let result = self.storage.commit();
+        self.check_code(owner, result)
+    }
+
+    // Undoes every change made since the innermost transaction began. Returns
+    // a Godot error code, `OK` on success
+    #[export]
+    pub fn rollback(&mut self, owner: &Node) -> i64 {
+        let result = self.storage.rollback();
+        self.check_code(owner, result)
+    }
+
+    // Sets how many steps of history are kept for `undo`, where `0` records no
+    // history
+    #[export]
+    pub fn set_history_depth(&mut self, _owner: &Node, depth: i64) {
+        self.storage.history_depth = depth.max(0) as usize;
+    }
+
+    // Undoes the most recent change. Returns a Godot error code, `OK` on
+    // success
+    #[export]
+    pub fn undo(&mut self, owner: &Node) -> i64 {
+        let result = self.storage.undo();
+        self.check_code(owner, result)
+    }
 
-        records
+    // Redoes the most recently undone change. Returns a Godot error code, `OK`
+    // on success
+    #[export]
+    pub fn redo(&mut self, owner: &Node) -> i64 {
+        let result = self.storage.redo();
+        self.check_code(owner, result)
+    }
+
+    #[export]
+    pub fn can_undo(&self, _owner: &Node) -> bool {
+        self.storage.can_undo()
+    }
+
+    #[export]
+    pub fn can_redo(&self, _owner: &Node) -> bool {
+        self.storage.can_redo()
+    }
+
+    // Names the current point in the history so it can be returned to with
+    // `revert_to`
+    #[export]
+    pub fn checkpoint(&mut self, _owner: &Node, name: String) {
+        self.storage.checkpoint(name);
+    }
+
+    // Undoes or redoes changes until the database is as it was at a
+    // checkpoint. Returns a Godot error code, `OK` on success
+    #[export]
+    pub fn revert_to(&mut self, owner: &Node, name: String) -> i64 {
+        let result = self.storage.revert_to(&name);
+        self.check_code(owner, result)
+    }
+
+    // Enables or disables journal mode, where every change is appended to a
+    // journal next to the save file as it happens instead of waiting for `save`
+    #[export]
+    pub fn set_journal(&mut self, _owner: &Node, enabled: bool) {
+        self.storage.journal = enabled;
+    }
+
+    // Folds the journal into a fresh save file. Returns a Godot error code,
+    // `OK` on success
+    #[export]
+    pub fn compact(&mut self, owner: &Node) -> i64 {
+        let result = self.storage.compact();
+        self.check_code(owner, result)
+    }
+
+    // Writes a human-readable JSON snapshot of the database to a path, without
+    // changing where `save` writes to. Returns a Godot error code, `OK` on
+    // success
+    #[export]
+    pub fn export_json(&mut self, owner: &Node, path: String) -> i64 {
+        let result = self.storage.export_db(globalize_path(&path), Format::Json);
+        self.check_code(owner, result)
     }
+
+    // Replaces the database with one loaded from a path, leaving it unchanged
+    // on failure. Returns a Godot error code, `OK` on success.
+    //
+    // If the loaded records break a unique constraint, the database is still
+    // replaced but `ERR_ALREADY_EXISTS` is returned and the constraint isn't
+    // enforced until a later load where they don't
+    #[export]
+    pub fn load(&mut self, owner: &Node, path: String) -> i64 {
+        let path = globalize_path(&path);
+
+        let result = Database::from(path.clone()).and_then(|db| self.replace_storage(db, path));
+        self.check_code(owner, result)
+    }
+
+    // Loads the database from a path, or starts a new empty database saving to
+    // that path if it doesn't exist yet. Returns a Godot error code, `OK` on
+    // success, see `load`
+    #[export]
+    pub fn open_or_create(&mut self, owner: &Node, path: String) -> i64 {
+        let path = globalize_path(&path);
+
+        let result =
+            Database::auto_from(path.clone(), false).and_then(|db| self.replace_storage(db, path));
+        self.check_code(owner, result)
+    }
+
+    // The description of the error from the last call that failed, or an
+    // empty string if it succeeded
+    #[export]
+    pub fn last_error(&self, _owner: &Node) -> String {
+        self.last_error.clone()
+    }
+
+    /// Replaces the database with a loaded one saving to `path`, moving
+    /// change signals, live queries, path indexes, unique constraints, search,
+    /// schemas and on-destroy rules over to it.
+    ///
+    /// The database is always replaced, but the first unique constraint the
+    /// loaded records already break is returned as an error. Broken
+    /// constraints aren't enforced, though they stay declared so a later load
+    /// applies them again.
+    fn replace_storage(
+        &mut self,
+        mut db: Database<Record>,
+        path: PathBuf,
+    ) -> Result<(), DatabaseError> {
+        let mut result = Ok(());
+
+        db.save_path = Some(path);
+        watch(&mut db, &self.changes);
+
+        for (name, path) in self.path_indexes.iter() {
+            db.create_path_index(name.clone(), path.clone());
+        }
+
+        for (name, (path, model)) in self.unique_paths.iter() {
+            let scope = Some(model.as_str()).filter(|model| !model.is_empty());
+            let created = db.create_unique_path(name.clone(), path.clone(), scope);
+
+            if result.is_ok() {
+                result = created;
+            }
+        }
+
+        if self.storage.search_enabled() {
+            db.enable_search();
+        }
+
+        db.schemas = std::mem::take(&mut self.storage.schemas);
+        db.relation_rules = std::mem::take(&mut self.storage.relation_rules);
+
+        for (_, subscription) in self.live_queries.iter_mut() {
+            *subscription = db.resubscribe(subscription);
+        }
+
+        self.storage = db;
+        result
+    }
+
+    /// Records the outcome of an operation, emitting a signal for each change
+    /// it made and the `error` signal if it failed.
+    fn check<T>(&mut self, owner: &Node, result: Result<T, DatabaseError>) -> Option<T> {
+        self.emit_changes(owner);
+
+        match result {
+            Ok(value) => {
+                self.last_error.clear();
+                Some(value)
+            }
+            Err(error) => {
+                self.last_error = error.to_string();
+                emit_deferred(owner, "error", &[self.last_error.to_variant()]);
+                None
+            }
+        }
+    }
+
+    /// Emits the signal for every change made since the last call, and the
+    /// `changed` signal of every live query whose results changed. Live
+    /// queries that have been freed are unsubscribed.
+    fn emit_changes(&mut self, owner: &Node) {
+        let changes = std::mem::take(&mut *self.changes.lock().unwrap());
+
+        for change in changes {
+            match change {
+                Change::Created(record) => emit_deferred(
+                    owner,
+                    "record_created",
+                    &[record.uuid.to_variant(), record.model.to_variant()],
+                ),
+                Change::Updated { old, new } => emit_deferred(
+                    owner,
+                    "record_updated",
+                    &[
+                        new.uuid.to_variant(),
+                        new.model.to_variant(),
+                        old.to_variant(),
+                        new.to_variant(),
+                    ],
+                ),
+                Change::Destroyed(record) => emit_deferred(
+                    owner,
+                    "record_destroyed",
+                    &[record.uuid.to_variant(), record.model.to_variant()],
+                ),
+            };
+        }
+
+        let storage = &mut self.storage;
+
+        self.live_queries.retain(|(id, subscription)| {
+            // SAFETY: the id is only used to look up the object, which is
+            // checked to still exist and be a `LiveQuery`
+            let live = unsafe { Reference::try_from_instance_id(*id) }
+                .and_then(TInstance::<LiveQuery, Shared>::try_from_base);
+
+            match live {
+                Some(live) => {
+                    let _ = live.map(|live, owner| live.emit_changed(&owner));
+                    true
+                }
+                None => {
+                    storage.unsubscribe(subscription);
+                    false
+                }
+            }
+        });
+    }
+
+    /// Like [GDDB::check], returning a Godot error code where `0` is `OK`.
+    fn check_code(&mut self, owner: &Node, result: Result<(), DatabaseError>) -> i64 {
+        let code = match &result {
+            Ok(()) => 0,
+            Err(error) => godot_error(error) as i64,
+        };
+
+        self.check(owner, result);
+        code
+    }
+}
+
+/// A live query started with `GDDB.live_query`, keeping its results up to
+/// date as records change.
+///
+/// The `changed` signal is emitted with the records that were added, removed
+/// and modified once the `GDDB` call making the changes returns, deferred
+/// like the signals of `GDDB`. Modified records are given as they are after
+/// the change.
+#[derive(NativeClass)]
+#[inherit(Reference)]
+#[no_constructor]
+#[register_with(Self::register_signals)]
+pub struct LiveQuery {
+    subscription: Subscription,
+}
+
+#[methods]
+impl LiveQuery {
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder
+            .signal("changed")
+            .with_param("added", VariantType::VariantArray)
+            .with_param("removed", VariantType::VariantArray)
+            .with_param("modified", VariantType::VariantArray)
+            .done();
+    }
+
+    // Every record currently matching the query
+    #[export]
+    pub fn results(&self, _owner: &Reference) -> Vec<Variant> {
+        self.subscription
+            .results()
+            .iter()
+            .map(|record| record.to_variant())
+            .collect()
+    }
+
+    /// Emits the `changed` signal if the results changed since the last call.
+    fn emit_changed(&self, owner: &Reference) {
+        let deltas = self.subscription.take_deltas();
+
+        if deltas.is_empty() {
+            return;
+        }
+
+        let added = VariantArray::new();
+        let removed = VariantArray::new();
+        let modified = VariantArray::new();
+
+        for delta in deltas {
+            match delta {
+                Delta::Added(record) => added.push(record.to_variant()),
+                Delta::Removed(record) => removed.push(record.to_variant()),
+                Delta::Modified { new, .. } => modified.push(new.to_variant()),
+            }
+        }
+
+        emit_deferred(
+            owner,
+            "changed",
+            &[
+                added.owned_to_variant(),
+                removed.owned_to_variant(),
+                modified.owned_to_variant(),
+            ],
+        );
+    }
+}
+
+/// A named collection of records returned by `GDDB.collection`.
+///
+/// Calls behave like those of the same name on `GDDB`, failing with the
+/// `error` signal of the `GDDB` node if the collection has been dropped.
+/// Changes to collections aren't emitted as signals or followed by live
+/// queries, which only cover the main database.
+#[derive(NativeClass)]
+#[inherit(Reference)]
+#[no_constructor]
+pub struct Collection {
+    gddb: i64,
+    name: String,
+}
+
+#[methods]
+impl Collection {
+    // The name of the collection
+    #[export]
+    pub fn name(&self, _owner: &Reference) -> String {
+        self.name.clone()
+    }
+
+    // Creates a record in the collection, returning its uuid
+    #[export]
+    pub fn create(
+        &self,
+        _owner: &Reference,
+        model: String,
+        attributes: Dictionary,
+    ) -> Option<String> {
+        let mut record = Record::new(model);
+        let uuid = record.uuid.clone();
+        record.attributes = value_from_dictionary(&attributes);
+
+        self.run(|collection| collection.create(record).map(|_| uuid))
+    }
+
+    // Finds a record in the collection given a uuid
+    #[export]
+    pub fn find(&self, _owner: &Reference, uuid: String) -> Option<Variant> {
+        self.run(|collection| collection.get(&uuid).map(|record| record.to_variant()))
+    }
+
+    // Updates a record in the collection, keeping its links
+    #[export]
+    pub fn update(
+        &self,
+        _owner: &Reference,
+        uuid: String,
+        model: String,
+        attributes: Dictionary,
+    ) -> i64 {
+        self.run_code(|collection| {
+            let original = collection.get(&uuid)?.clone();
+            let new = Record {
+                model,
+                attributes: value_from_dictionary(&attributes),
+                ..original.clone()
+            };
+
+            collection.update(&original, new)
+        })
+    }
+
+    // Deep merges the keys of a dictionary into the attributes of a record,
+    // see `GDDB.patch`
+    #[export]
+    pub fn patch(&self, _owner: &Reference, uuid: String, attributes: Dictionary) -> i64 {
+        let patch = value_from_dictionary(&attributes);
+
+        self.run_code(|collection| {
+            collection.modify(&uuid, |record| record.attributes.merge(patch))
+        })
+    }
+
+    // Removes a record from the collection by its uuid
+    #[export]
+    pub fn destroy(&self, _owner: &Reference, uuid: String) -> i64 {
+        self.run_code(|collection| collection.remove_by_key(&uuid).map(|_| ()))
+    }
+
+    // Every record in the collection
+    #[export]
+    pub fn all(&self, _owner: &Reference) -> Vec<Variant> {
+        self.run(|collection| {
+            Ok(collection
+                .items
+                .values()
+                .map(|record| record.to_variant())
+                .collect())
+        })
+        .unwrap_or_default()
+    }
+
+    // Finds all records in the collection matching a query string, see
+    // `GDDB.where`
+    #[export(name = "where")]
+    pub fn where_query(&self, _owner: &Reference, query: String) -> Vec<Variant> {
+        self.run(|collection| {
+            let query = Query::parse(&query)?;
+            Ok(records_to_variants(collection.filter(&query)))
+        })
+        .unwrap_or_default()
+    }
+
+    // Finds records in the collection in a stable order, see `GDDB.select`
+    #[export]
+    pub fn select(&self, _owner: &Reference, query: String, options: Dictionary) -> Vec<Variant> {
+        self.run(|collection| {
+            select_options(collection, &query, &options)
+                .map(|select| records_to_variants(select.records()))
+        })
+        .unwrap_or_default()
+    }
+
+    // The number of records in the collection
+    #[export]
+    pub fn count(&self, _owner: &Reference) -> i64 {
+        self.run(|collection| Ok(collection.items.len() as i64))
+            .unwrap_or_default()
+    }
+
+    // Registers the schema of a model in this collection, see
+    // `GDDB.define_model`. Unlike the main database, schemas of collections
+    // aren't kept across `GDDB.load_collections`
+    #[export]
+    pub fn define_model(&self, _owner: &Reference, name: String, schema: Dictionary) -> i64 {
+        let schema = value_from_dictionary(&schema);
+
+        self.run_code(|collection| {
+            collection.define_model(name, Schema::from_value(schema)?);
+            Ok(())
+        })
+    }
+
+    // Sets if creating a record that's identical to an existing one is an
+    // error in this collection
+    #[export]
+    pub fn set_strict_dupes(&self, _owner: &Reference, strict: bool) {
+        self.run(|collection| {
+            collection.strict_dupes = strict;
+            Ok(())
+        });
+    }
+
+    /// Runs `f` on the collection, recording its outcome on the `GDDB` node
+    /// like its own methods. Returns [Option::None] if either no longer
+    /// exists.
+    fn run<T>(
+        &self,
+        f: impl FnOnce(&mut Database<Record>) -> Result<T, DatabaseError>,
+    ) -> Option<T> {
+        self.with_gddb(|gddb, owner| {
+            let result = gddb.collections.collection_mut(&self.name).and_then(f);
+            gddb.check(owner, result)
+        })
+        .flatten()
+    }
+
+    /// Like [Collection::run], returning a Godot error code where `0` is
+    /// `OK`.
+    fn run_code(&self, f: impl FnOnce(&mut Database<Record>) -> Result<(), DatabaseError>) -> i64 {
+        self.with_gddb(|gddb, owner| {
+            let result = gddb.collections.collection_mut(&self.name).and_then(f);
+            gddb.check_code(owner, result)
+        })
+        .unwrap_or(GodotError::DoesNotExist as i64)
+    }
+
+    /// Runs `f` on the `GDDB` node the collection came from, if it still
+    /// exists.
+    fn with_gddb<T>(&self, f: impl FnOnce(&mut GDDB, &Node) -> T) -> Option<T> {
+        // SAFETY: the id is only used to look up the object, which is checked
+        // to still exist and be a `GDDB`
+        let gddb = unsafe { Node::try_from_instance_id(self.gddb) }
+            .and_then(TInstance::<GDDB, Shared>::try_from_base)?;
+
+        gddb.map_mut(|gddb, owner| f(gddb, &owner)).ok()
+    }
+}
+
+/// Emits a signal once Godot is idle rather than straight away, as handlers
+/// calling back into a `GDDB` node would fail while it's still borrowed by
+/// the call that made the change.
+fn emit_deferred(owner: &Object, signal: &str, args: &[Variant]) {
+    let mut varargs = vec![signal.to_variant()];
+    varargs.extend_from_slice(args);
+
+    // SAFETY: `emit_signal` is a method of every object, and is given the
+    // arguments the signal was registered with
+    unsafe { owner.call_deferred("emit_signal", &varargs) };
+}
+
+/// Queues every change made to a database, to be emitted as signals by
+/// [GDDB::emit_changes].
+fn watch(storage: &mut Database<Record>, changes: &Arc<Mutex<Vec<Change<Record>>>>) {
+    let changes = changes.clone();
+
+    storage.observe(move |change| changes.lock().unwrap().push(change.clone()));
+}
+
+/// Converts records into their dictionaries.
+fn records_to_variants(records: Vec<&Record>) -> Vec<Variant> {
+    records.iter().map(|record| record.to_variant()).collect()
+}
+
+/// Builds a [Select] from a query string and the options given to
+/// [GDDB::select].
+fn select_options<'a>(
+    storage: &'a Database<Record>,
+    query: &str,
+    options: &Dictionary,
+) -> Result<Select<'a>, DatabaseError> {
+    let mut select = storage.select();
+
+    if !query.trim().is_empty() {
+        select = select.filter(Query::parse(query)?);
+    }
+
+    if let Some(order) = options.get("order_by") {
+        for (path, order) in parse_order(&order.to_string())? {
+            select = select.order_by(path, order);
+        }
+    }
+
+    if let Some(after) = options.get("after").filter(|after| !after.is_nil()) {
+        select = select.after(Cursor::decode(&after.to_string())?);
+    }
+
+    if let Some(offset) = options
+        .get("offset")
+        .and_then(|offset| offset.try_to::<i64>().ok())
+    {
+        select = select.offset(offset.max(0) as usize);
+    }
+
+    if let Some(limit) = options
+        .get("limit")
+        .and_then(|limit| limit.try_to::<i64>().ok())
+    {
+        select = select.limit(limit.max(0) as usize);
+    }
+
+    Ok(select)
+}
+
+/// Computes the aggregates given to [GDDB::aggregate].
+fn aggregate_spec(
+    storage: &Database<Record>,
+    query: &str,
+    spec: &Dictionary,
+) -> Result<Dictionary, DatabaseError> {
+    let mut select = storage.select();
+
+    if !query.trim().is_empty() {
+        select = select.filter(Query::parse(query)?);
+    }
+
+    let mut aggregates = Vec::new();
+    let mut group_by = None;
+
+    for (name, aggregate) in spec.iter() {
+        let name = name.to_string();
+
+        if name == "group_by" {
+            group_by = Some(aggregate.to_string());
+        } else {
+            aggregates.push((name, Aggregate::parse(&aggregate.to_string())?));
+        }
+    }
+
+    let results = |records: &[&Record]| {
+        aggregates
+            .iter()
+            .map(|(name, aggregate)| Ok((name.as_str(), aggregate.apply(records)?)))
+            .collect::<Result<Vec<_>, DatabaseError>>()
+            .map(|results| results.into_iter().collect::<Dictionary<Unique>>())
+    };
+
+    let data = match group_by {
+        Some(path) => select
+            .group_by(&path)
+            .iter()
+            .map(|(group, records)| Ok((group, results(records)?)))
+            .collect::<Result<Vec<_>, DatabaseError>>()?
+            .into_iter()
+            .collect::<Dictionary<Unique>>(),
+        None => results(&select.records())?,
+    };
+
+    Ok(data.into_shared())
+}
+
+/// Converts a Godot path such as `user://save.gddb` into an absolute path on
+/// disk. Regular paths are returned unchanged.
+fn globalize_path(path: &str) -> PathBuf {
+    let settings = ProjectSettings::godot_singleton();
+
+    PathBuf::from(settings.globalize_path(path).to_string())
+}
+
+/// Maps a [DatabaseError] onto the closest [GodotError].
+fn godot_error(error: &DatabaseError) -> GodotError {
+    match error {
+        DatabaseError::ItemNotFound => GodotError::DoesNotExist,
+        DatabaseError::IndexNotFound => GodotError::DoesNotExist,
+        DatabaseError::DupeFound => GodotError::AlreadyExists,
+        DatabaseError::SavePathRequired => GodotError::FileBadPath,
+        DatabaseError::IOError(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            GodotError::FileCorrupt
+        }
+        DatabaseError::IOError(_) => GodotError::FileCantOpen,
+        DatabaseError::DatabaseNotFound => GodotError::FileNotFound,
+        DatabaseError::BadDbName => GodotError::FileBadPath,
+        DatabaseError::BadQuery(_) => GodotError::ParseError,
+        DatabaseError::CorruptFile => GodotError::FileCorrupt,
+        DatabaseError::CorruptCollection(_) => GodotError::FileCorrupt,
+        DatabaseError::VersionMismatch(_) => GodotError::FileUnrecognized,
+        DatabaseError::TypeMismatch(_) => GodotError::FileUnrecognized,
+        DatabaseError::NoTransaction => GodotError::DoesNotExist,
+        DatabaseError::TransactionOpen => GodotError::Busy,
+        DatabaseError::NoHistory => GodotError::DoesNotExist,
+        DatabaseError::CheckpointNotFound => GodotError::DoesNotExist,
+        DatabaseError::WrongType(_) => GodotError::InvalidData,
+        DatabaseError::CollectionNotFound(_) => GodotError::DoesNotExist,
+        DatabaseError::ValidationFailed { .. } => GodotError::InvalidParameter,
+        DatabaseError::UniqueViolation(_) => GodotError::AlreadyExists,
+        DatabaseError::RelationRestricted(_) => GodotError::AlreadyInUse,
+        DatabaseError::BrokenReference(_) => GodotError::DoesNotExist,
+    }
+}
+
+/// Converts a record into a `{ uuid, model, attributes, links }` dictionary,
+/// with each link as a `{ model, uuid }` dictionary under its relation.
+impl ToVariant for Record {
+    fn to_variant(&self) -> Variant {
+        let data = Dictionary::new();
+        let links = Dictionary::new();
+
+        for (relation, link) in self.links.iter() {
+            let reference = Dictionary::new();
+            reference.insert("model", link.model.clone());
+            reference.insert("uuid", link.uuid.clone());
+            links.insert(relation.as_str(), reference.into_shared());
+        }
+
+        data.insert("uuid", self.uuid.clone());
+        data.insert("model", self.model.clone());
+        data.insert("attributes", &self.attributes);
+        data.insert("links", links.into_shared());
+
+        data.owned_to_variant()
+    }
+}
+
+impl ToVariant for Value {
+    fn to_variant(&self) -> Variant {
+        match self {
+            Value::Null => Variant::nil(),
+            Value::Bool(b) => b.to_variant(),
+            Value::Int(i) => i.to_variant(),
+            Value::Float(f) => f.to_variant(),
+            Value::String(s) => s.to_variant(),
+            Value::Array(items) => items
+                .iter()
+                .collect::<VariantArray<Unique>>()
+                .owned_to_variant(),
+            Value::Map(map) => map
+                .iter()
+                .map(|(key, value)| (key.as_str(), value))
+                .collect::<Dictionary<Unique>>()
+                .owned_to_variant(),
+        }
+    }
+}
+
+/// Converts any variant losslessly, apart from Godot-specific types such as
+/// vectors, colours and objects which are stored in their string form. Pool
+/// arrays become a [Value::Array].
+impl FromVariant for Value {
+    fn from_variant(variant: &Variant) -> Result<Self, FromVariantError> {
+        let value = match variant.dispatch() {
+            VariantDispatch::Nil => Value::Null,
+            VariantDispatch::Bool(b) => Value::Bool(b),
+            VariantDispatch::I64(i) => Value::Int(i),
+            VariantDispatch::F64(f) => Value::Float(f),
+            VariantDispatch::GodotString(s) => Value::String(s.to_string()),
+            VariantDispatch::Dictionary(dictionary) => value_from_dictionary(&dictionary),
+            VariantDispatch::VariantArray(items) => value_from_array(&items),
+            VariantDispatch::ByteArray(_)
+            | VariantDispatch::Int32Array(_)
+            | VariantDispatch::Float32Array(_)
+            | VariantDispatch::StringArray(_)
+            | VariantDispatch::Vector2Array(_)
+            | VariantDispatch::Vector3Array(_)
+            | VariantDispatch::ColorArray(_) => {
+                value_from_array(&variant.coerce_to::<VariantArray>())
+            }
+            _ => Value::String(variant.to_string()),
+        };
+
+        Ok(value)
+    }
+}
+
+/// Converts a Godot dictionary into a [Value::Map], with keys in their
+/// string form.
+fn value_from_dictionary(dictionary: &Dictionary) -> Value {
+    Value::Map(
+        dictionary
+            .iter()
+            .map(|(key, value)| (key.to_string(), value_from_variant(&value)))
+            .collect(),
+    )
+}
+
+/// Converts a Godot array into a [Value::Array].
+fn value_from_array(items: &VariantArray) -> Value {
+    Value::Array(items.iter().map(|item| value_from_variant(&item)).collect())
+}
+
+/// Converts a variant into a [Value], see the [FromVariant] implementation.
+fn value_from_variant(variant: &Variant) -> Value {
+    Value::from_variant(variant).unwrap_or_default()
 }
@@ -0,0 +1,193 @@
+//! The header written at the start of every `.gddb` file.
+
+use crate::prelude::*;
+
+/// Magic bytes identifying a GDDB file.
+pub const MAGIC: &[u8; 4] = b"GDDB";
+
+/// The current version of the file layout written by [crate::Database::dump_db].
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Describes the payload of a `.gddb` file.
+///
+/// The header is laid out as the [MAGIC] bytes followed by the little-endian
+/// `format_version`, `schema_version`, length of `type_name`, `type_name`
+/// itself and finally the `checksum` of the payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHeader {
+    /// Version of the file layout, see [FORMAT_VERSION].
+    pub format_version: u32,
+
+    /// Version of the stored items, see [crate::Database::register_migration].
+    pub schema_version: u32,
+
+    /// Name of the stored item type, see [Keyed::type_name].
+    pub type_name: String,
+
+    /// CRC-32 checksum of the payload following the header.
+    pub checksum: u32,
+}
+
+impl FileHeader {
+    /// Creates a header describing `payload`.
+    pub fn new(schema_version: u32, type_name: impl Into<String>, payload: &[u8]) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            schema_version,
+            type_name: type_name.into(),
+            checksum: crc32(payload),
+        }
+    }
+
+    /// Writes the header followed by the payload.
+    pub fn encode(&self, payload: &[u8]) -> Vec<u8> {
+        let mut stream = Vec::with_capacity(payload.len() + self.type_name.len() + 20);
+
+        stream.extend_from_slice(MAGIC);
+        stream.extend_from_slice(&self.format_version.to_le_bytes());
+        stream.extend_from_slice(&self.schema_version.to_le_bytes());
+        stream.extend_from_slice(&(self.type_name.len() as u32).to_le_bytes());
+        stream.extend_from_slice(self.type_name.as_bytes());
+        stream.extend_from_slice(&self.checksum.to_le_bytes());
+        stream.extend_from_slice(payload);
+
+        stream
+    }
+
+    /// Reads a header from the start of a file, returning it along with the
+    /// payload after it.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::CorruptFile] if the file isn't a GDDB file
+    /// or the payload doesn't match its checksum, and
+    /// [DatabaseError::VersionMismatch] if the file was written by a newer
+    /// version of GDDB.
+    pub fn decode(stream: &[u8]) -> Result<(Self, &[u8]), DatabaseError> {
+        let mut reader = stream;
+
+        if take(&mut reader, MAGIC.len())? != MAGIC {
+            return Err(DatabaseError::CorruptFile);
+        }
+
+        let format_version = take_u32(&mut reader)?;
+
+        if format_version > FORMAT_VERSION {
+            return Err(DatabaseError::VersionMismatch(format_version));
+        }
+
+        let schema_version = take_u32(&mut reader)?;
+        let name_len = take_u32(&mut reader)? as usize;
+        let type_name = String::from_utf8(take(&mut reader, name_len)?.to_vec())
+            .map_err(|_| DatabaseError::CorruptFile)?;
+        let checksum = take_u32(&mut reader)?;
+
+        if crc32(reader) != checksum {
+            return Err(DatabaseError::CorruptFile);
+        }
+
+        let header = Self {
+            format_version,
+            schema_version,
+            type_name,
+            checksum,
+        };
+
+        Ok((header, reader))
+    }
+}
+
+/// Splits `len` bytes off the front of `reader`.
+fn take<'a>(reader: &mut &'a [u8], len: usize) -> Result<&'a [u8], DatabaseError> {
+    if reader.len() < len {
+        return Err(DatabaseError::CorruptFile);
+    }
+
+    let (taken, rest) = reader.split_at(len);
+    *reader = rest;
+
+    Ok(taken)
+}
+
+/// Splits a little-endian [u32] off the front of `reader`.
+fn take_u32(reader: &mut &[u8]) -> Result<u32, DatabaseError> {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(take(reader, 4)?);
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Lookup table for [crc32], using the IEEE polynomial.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+};
+
+/// Calculates the CRC-32 checksum of some bytes.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, byte| {
+        CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests [crc32] against a known checksum
+    #[test]
+    fn crc32_check() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    /// Tests a header survives encoding, and that damaged files are rejected
+    #[test]
+    fn header_round_trip() -> Result<(), DatabaseError> {
+        let header = FileHeader::new(3, "Record", b"payload");
+        let stream = header.encode(b"payload");
+
+        let (decoded, payload) = FileHeader::decode(&stream)?;
+        assert_eq!(decoded, header);
+        assert_eq!(payload, b"payload");
+
+        let mut damaged = stream.clone();
+        *damaged.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            FileHeader::decode(&damaged),
+            Err(DatabaseError::CorruptFile)
+        ));
+
+        assert!(matches!(
+            FileHeader::decode(&stream[..10]),
+            Err(DatabaseError::CorruptFile)
+        ));
+
+        let mut newer = stream;
+        newer[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            FileHeader::decode(&newer),
+            Err(DatabaseError::VersionMismatch(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,340 @@
+//! Undo and redo history, letting in-game editors step backwards and forwards
+//! through the changes made to a [Database].
+//!
+//! History is recorded once [Database::history_depth] is set. Each call to
+//! [Database::create], [Database::update], [Database::destroy] and so on is a
+//! single step, as is every change made by a committed transaction. Named
+//! checkpoints can be made with [Database::checkpoint] and returned to with
+//! [Database::revert_to].
+//!
+//! ```rust
+//! use gddb::{Database, Record};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("LEVEL", None, false);
+//!     db.history_depth = 100;
+//!
+//!     db.checkpoint("empty");
+//!     db.create(Record::new("Wall".into())).unwrap();
+//!     db.create(Record::new("Door".into())).unwrap();
+//!
+//!     db.undo().unwrap();
+//!     assert_eq!(db.len(), 1);
+//!
+//!     db.revert_to("empty").unwrap();
+//!     assert_eq!(db.len(), 0);
+//! }
+//! ```
+
+use crate::prelude::*;
+use std::collections::VecDeque;
+
+/// A change made to a single item, recording the item before and after.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Edit<K, T> {
+    pub(crate) key: K,
+    pub(crate) before: Option<T>,
+    pub(crate) after: Option<T>,
+}
+
+/// The undo and redo stacks of a [Database].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct History<T: Keyed> {
+    /// Steps that can be undone, oldest first.
+    undo: VecDeque<Vec<Edit<T::Key, T>>>,
+
+    /// Steps that can be redone, most recently undone last.
+    redo: Vec<Vec<Edit<T::Key, T>>>,
+
+    /// How many of the oldest steps have been dropped to keep within
+    /// [Database::history_depth].
+    trimmed: usize,
+
+    /// Named positions in the history, counted in steps from the start.
+    checkpoints: HashMap<String, usize>,
+}
+
+impl<T: Keyed> History<T> {
+    fn new() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            trimmed: 0,
+            checkpoints: HashMap::new(),
+        }
+    }
+
+    /// The current position in the history, counted in steps from the start.
+    fn position(&self) -> usize {
+        self.trimmed + self.undo.len()
+    }
+}
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
+    /// Undoes the most recent step.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::NoHistory] if there is nothing to undo,
+    /// [DatabaseError::TransactionOpen] if a transaction is open, or
+    /// [DatabaseError::UniqueViolation] without undoing anything if an item
+    /// it restores would share a value with an item changed since.
+    pub fn undo(&mut self) -> Result<(), DatabaseError> {
+        if self.in_transaction() {
+            return Err(DatabaseError::TransactionOpen);
+        }
+
+        let step = self
+            .history
+            .as_mut()
+            .and_then(|history| history.undo.pop_back())
+            .ok_or(DatabaseError::NoHistory)?;
+
+        let states = step
+            .iter()
+            .rev()
+            .map(|edit| (edit.key.clone(), edit.before.clone()));
+        let result = self.apply_states(states.collect());
+        let history = self.history.as_mut().unwrap();
+
+        match result {
+            Ok(()) => history.redo.push(step),
+            Err(_) => history.undo.push_back(step),
+        }
+
+        result
+    }
+
+    /// Redoes the most recently undone step.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::NoHistory] if there is nothing to redo, or
+    /// [DatabaseError::TransactionOpen] and [DatabaseError::UniqueViolation]
+    /// like [Database::undo].
+    pub fn redo(&mut self) -> Result<(), DatabaseError> {
+        if self.in_transaction() {
+            return Err(DatabaseError::TransactionOpen);
+        }
+
+        let step = self
+            .history
+            .as_mut()
+            .and_then(|history| history.redo.pop())
+            .ok_or(DatabaseError::NoHistory)?;
+
+        let states = step
+            .iter()
+            .map(|edit| (edit.key.clone(), edit.after.clone()));
+        let result = self.apply_states(states.collect());
+        let history = self.history.as_mut().unwrap();
+
+        match result {
+            Ok(()) => history.undo.push_back(step),
+            Err(_) => history.redo.push(step),
+        }
+
+        result
+    }
+
+    /// If there is a step that can be undone with [Database::undo].
+    pub fn can_undo(&self) -> bool {
+        self.history
+            .as_ref()
+            .is_some_and(|history| !history.undo.is_empty())
+    }
+
+    /// If there is a step that can be redone with [Database::redo].
+    pub fn can_redo(&self) -> bool {
+        self.history
+            .as_ref()
+            .is_some_and(|history| !history.redo.is_empty())
+    }
+
+    /// Names the current position in the history, so it can be returned to
+    /// with [Database::revert_to]. An existing checkpoint with the same name
+    /// is moved.
+    pub fn checkpoint(&mut self, name: impl Into<String>) {
+        let history = self.history.get_or_insert_with(History::new);
+        let position = history.position();
+
+        history.checkpoints.insert(name.into(), position);
+    }
+
+    /// Undoes or redoes steps until the database is as it was when
+    /// [Database::checkpoint] was called with `name`.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::CheckpointNotFound] if there's no such
+    /// checkpoint, or it was lost by making changes after undoing past it or
+    /// by going over [Database::history_depth].
+    pub fn revert_to(&mut self, name: &str) -> Result<(), DatabaseError> {
+        let target = self
+            .history
+            .as_ref()
+            .and_then(|history| history.checkpoints.get(name).copied())
+            .ok_or(DatabaseError::CheckpointNotFound)?;
+
+        while let Some(history) = self.history.as_ref() {
+            let position = history.position();
+
+            if position > target {
+                self.undo()?;
+            } else if position < target {
+                self.redo()?;
+            } else {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forgets every step and checkpoint.
+    pub fn clear_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Records a step in the history, if [Database::history_depth] is set.
+    pub(crate) fn record_history(&mut self, step: Vec<Edit<Record::Key, Record>>) {
+        if self.history_depth == 0 || step.is_empty() {
+            return;
+        }
+
+        let depth = self.history_depth;
+        let history = self.history.get_or_insert_with(History::new);
+        let position = history.position();
+
+        history.redo.clear();
+        history.checkpoints.retain(|_, p| *p <= position);
+        history.undo.push_back(step);
+
+        while history.undo.len() > depth {
+            history.undo.pop_front();
+            history.trimmed += 1;
+        }
+
+        let trimmed = history.trimmed;
+        history.checkpoints.retain(|_, p| *p >= trimmed);
+    }
+
+    /// If changes should currently be recorded as [Edit]s, for either a
+    /// transaction, the history or observers.
+    pub(crate) fn tracking_edits(&self) -> bool {
+        self.transaction.is_some() || self.history_depth > 0 || !self.observers.is_empty()
+    }
+
+    /// Sets items to the given states as a single transaction, removing
+    /// them where the state is [Option::None], without recording history.
+    /// Unique constraints are checked against each state in turn.
+    fn apply_states(
+        &mut self,
+        states: Vec<(Record::Key, Option<Record>)>,
+    ) -> Result<(), DatabaseError> {
+        let depth = std::mem::replace(&mut self.history_depth, 0);
+
+        let result = self.transaction(|db| {
+            for (key, state) in states {
+                match state {
+                    Some(item) => {
+                        db.check_unique(&item, &[&key])?;
+                        db.insert_item(item)?
+                    }
+                    None => db.remove_item(&key)?,
+                };
+            }
+
+            Ok(())
+        });
+
+        self.history_depth = depth;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests steps can be undone and redone, with transactions and updates
+    /// being a single step
+    #[test]
+    fn undo_redo() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("History test", None, false);
+        db.history_depth = 10;
+
+        let wall = Record::new("Wall".into());
+        let mut moved = wall.clone();
+        moved.set("x", 5);
+
+        db.create(wall.clone())?;
+        db.update(&wall, moved.clone())?;
+        db.transaction(|db| {
+            db.create(Record::new("Door".into()))?;
+            db.create(Record::new("Door".into()))
+        })?;
+        assert_eq!(db.len(), 3);
+
+        db.undo()?;
+        assert_eq!(db.len(), 1);
+        db.undo()?;
+        assert_eq!(db.get(&wall.uuid)?, &wall);
+
+        db.begin();
+        assert!(matches!(db.redo(), Err(DatabaseError::TransactionOpen)));
+        assert!(matches!(db.undo(), Err(DatabaseError::TransactionOpen)));
+        db.commit()?;
+
+        db.redo()?;
+        assert_eq!(db.get(&wall.uuid)?, &moved);
+        assert!(db.can_redo());
+
+        db.destroy(&moved)?;
+        assert!(!db.can_redo());
+        assert!(matches!(db.redo(), Err(DatabaseError::NoHistory)));
+
+        db.undo()?;
+        db.undo()?;
+        db.undo()?;
+        assert_eq!(db.len(), 0);
+        assert!(!db.can_undo());
+
+        Ok(())
+    }
+
+    /// Tests reverting to checkpoints, and that checkpoints are lost when
+    /// their step is dropped from the history
+    #[test]
+    fn checkpoints() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("History test", None, false);
+        db.history_depth = 2;
+
+        db.checkpoint("start");
+        db.create(Record::new("Wall".into()))?;
+        db.checkpoint("one");
+        db.create(Record::new("Wall".into()))?;
+        db.create(Record::new("Wall".into()))?;
+        db.checkpoint("three");
+
+        assert!(matches!(
+            db.revert_to("start"),
+            Err(DatabaseError::CheckpointNotFound)
+        ));
+
+        db.revert_to("one")?;
+        assert_eq!(db.len(), 1);
+
+        db.revert_to("three")?;
+        assert_eq!(db.len(), 3);
+
+        db.revert_to("one")?;
+        db.create(Record::new("Door".into()))?;
+        assert!(matches!(
+            db.revert_to("three"),
+            Err(DatabaseError::CheckpointNotFound)
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+//! Secondary indexes that can be declared on a [crate::Database].
+//!
+//! An [Index] looks up items sharing an exact value, while an [OrderedIndex]
+//! keeps its values sorted so it can also answer range scans, string prefix
+//! matches and minimum or maximum lookups without scanning the database.
+
+use crate::prelude::*;
+use crate::query::resolve;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+/// Extracts the value an item is indexed by inside of an [Index].
+///
+/// This is commonly a non-capturing closure like `|r: &Record| r.model.clone()`.
+pub type IndexFn<T> = fn(&T) -> String;
+
+/// A named secondary index, mapping an extracted value to the primary keys of
+/// every item sharing that value.
+///
+/// Indexes are kept in sync by [crate::Database] and are not saved when
+/// dumping.
+#[derive(Debug, Clone)]
+pub struct Index<T: Keyed> {
+    extract: IndexFn<T>,
+    entries: HashMap<String, HashSet<T::Key>>,
+}
+
+impl<T: Keyed> Index<T> {
+    /// Creates a new, empty index using the given extractor.
+    pub fn new(extract: IndexFn<T>) -> Self {
+        Self {
+            extract,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Adds an item to the index.
+    pub fn insert(&mut self, item: &T) {
+        self.entries
+            .entry((self.extract)(item))
+            .or_default()
+            .insert(item.primary_key());
+    }
+
+    /// Removes an item from the index, dropping the entry once it is empty.
+    pub fn remove(&mut self, item: &T) {
+        let value = (self.extract)(item);
+
+        if let Some(keys) = self.entries.get_mut(&value) {
+            keys.remove(&item.primary_key());
+
+            if keys.is_empty() {
+                self.entries.remove(&value);
+            }
+        }
+    }
+
+    /// Removes every item from the index.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns the primary keys of all items indexed under `value`.
+    pub fn lookup(&self, value: &str) -> impl Iterator<Item = &T::Key> {
+        self.entries.get(value).into_iter().flatten()
+    }
+}
+
+impl<T: Keyed> PartialEq for Index<T> {
+    /// Indexes are equal when they hold the same entries, extractors can't be
+    /// meaningfully compared.
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+/// Extracts the value an item is sorted by inside of an [OrderedIndex].
+///
+/// Unlike an [IndexFn], this may capture variables such as an attribute
+/// path.
+pub type OrderedFn<T> = Arc<dyn Fn(&T) -> Value + Send + Sync>;
+
+/// A [Value] ordered by [Value::total_cmp], so it can be used as a key.
+#[derive(Debug, Clone)]
+struct Sorted(Value);
+
+impl PartialEq for Sorted {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Sorted {}
+
+impl PartialOrd for Sorted {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Sorted {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A named secondary index keeping the primary keys of every item sorted by
+/// an extracted [Value], see [crate::Database::create_ordered_index].
+///
+/// Values are sorted with [Value::total_cmp], and items whose value is
+/// [Value::Null] aren't indexed.
+#[derive(Clone)]
+pub struct OrderedIndex<T: Keyed> {
+    extract: OrderedFn<T>,
+    entries: BTreeMap<Sorted, HashSet<T::Key>>,
+}
+
+impl<T: Keyed> OrderedIndex<T> {
+    /// Creates a new, empty index using the given extractor.
+    pub fn new(extract: OrderedFn<T>) -> Self {
+        Self {
+            extract,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an item to the index.
+    pub fn insert(&mut self, item: &T) {
+        let value = (self.extract)(item);
+
+        if !value.is_null() {
+            self.entries
+                .entry(Sorted(value))
+                .or_default()
+                .insert(item.primary_key());
+        }
+    }
+
+    /// Removes an item from the index, dropping the entry once it is empty.
+    pub fn remove(&mut self, item: &T) {
+        let value = Sorted((self.extract)(item));
+
+        if let Some(keys) = self.entries.get_mut(&value) {
+            keys.remove(&item.primary_key());
+
+            if keys.is_empty() {
+                self.entries.remove(&value);
+            }
+        }
+    }
+
+    /// Removes every item from the index.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns the primary keys of all items with a value inside of `range`,
+    /// smallest value first.
+    pub fn range(&self, range: impl RangeBounds<Value>) -> Vec<&T::Key> {
+        let start = sorted_bound(range.start_bound());
+        let end = sorted_bound(range.end_bound());
+
+        let empty = match (&start, &end) {
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Included(start), Bound::Excluded(end))
+            | (Bound::Excluded(start), Bound::Included(end))
+            | (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+            _ => false,
+        };
+
+        if empty {
+            return Vec::new();
+        }
+
+        self.entries
+            .range((start, end))
+            .flat_map(|(_, keys)| keys)
+            .collect()
+    }
+
+    /// Returns the primary keys of all items with a string value starting
+    /// with `prefix`, in order.
+    pub fn prefix(&self, prefix: &str) -> Vec<&T::Key> {
+        self.entries
+            .range(Sorted(Value::from(prefix))..)
+            .take_while(|(value, _)| value.0.as_str().is_some_and(|s| s.starts_with(prefix)))
+            .flat_map(|(_, keys)| keys)
+            .collect()
+    }
+
+    /// Returns the primary keys of all items with the same value as `item`,
+    /// which doesn't need to be in the index. Nothing has the same value as an
+    /// item whose value is [Value::Null].
+    pub fn same_value(&self, item: &T) -> Vec<&T::Key> {
+        self.entries
+            .get(&Sorted((self.extract)(item)))
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Returns the primary keys of all items sharing the smallest value.
+    pub fn min(&self) -> Vec<&T::Key> {
+        self.entries.values().next().into_iter().flatten().collect()
+    }
+
+    /// Returns the primary keys of all items sharing the largest value.
+    pub fn max(&self) -> Vec<&T::Key> {
+        self.entries
+            .values()
+            .next_back()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+impl<T: Keyed> PartialEq for OrderedIndex<T> {
+    /// Indexes are equal when they hold the same entries, extractors can't be
+    /// meaningfully compared.
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl<T: Keyed> fmt::Debug for OrderedIndex<T>
+where
+    T::Key: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OrderedIndex")
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+/// Converts a bound on a [Value] into a bound on its [Sorted] key.
+fn sorted_bound(bound: Bound<&Value>) -> Bound<Sorted> {
+    match bound {
+        Bound::Included(value) => Bound::Included(Sorted(value.clone())),
+        Bound::Excluded(value) => Bound::Excluded(Sorted(value.clone())),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl Database<Record> {
+    /// Declares a named [OrderedIndex] over the value at a path, which is
+    /// resolved the same way as paths in a [Query].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::{Database, Record, Value};
+    ///
+    /// fn main() {
+    ///     let mut db: Database<Record> = Database::new("GAME", None, false);
+    ///     db.create_path_index("by_level", "level");
+    ///
+    ///     for level in 1..=20 {
+    ///         let mut enemy = Record::new("Enemy".into());
+    ///         enemy.set("level", level);
+    ///         db.create(enemy).unwrap();
+    ///     }
+    ///
+    ///     let enemies = db.query_range("by_level", Value::from(5)..=Value::from(10));
+    ///     assert_eq!(enemies.unwrap().len(), 6);
+    /// }
+    /// ```
+    pub fn create_path_index(&mut self, name: impl Into<String>, path: impl Into<String>) {
+        let path = path.into();
+
+        self.create_ordered_index(name, move |record: &Record| {
+            resolve(record, &path).map_or(Value::Null, |value| value.into_owned())
+        });
+    }
+}
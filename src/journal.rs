@@ -0,0 +1,199 @@
+//! An append-only journal of changes, letting a [Database] persist each change
+//! as it happens rather than rewriting the whole save with
+//! [Database::dump_db].
+//!
+//! When [Database::journal] is enabled, every change is appended to a
+//! `[save path].journal` file and flushed to disk before it is made in memory.
+//! Loading the save with [Database::from] replays the journal on top of it,
+//! or on top of an empty database with [Database::auto_from] if nothing has
+//! been saved yet, and [Database::compact] folds the journal into a fresh
+//! save.
+//!
+//! Each entry is written as its length and CRC-32 checksum followed by the
+//! entry itself, so a partially written entry left behind by a crash is
+//! detected and ignored when replaying.
+
+use crate::prelude::*;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+/// A single change recorded in a journal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Entry {
+    /// An item was inserted, replacing any item with the same key.
+    Insert(Value),
+
+    /// The item with the given key was removed.
+    Remove(Value),
+
+    /// Every change made by a committed transaction, see [crate::transaction].
+    Batch(Vec<Entry>),
+}
+
+/// An [Entry] along with the schema version its item was written with, so it
+/// can be migrated when replayed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Frame {
+    schema_version: u32,
+    entry: Entry,
+}
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
+    /// Folds the journal into a fresh save, see [crate::journal].
+    ///
+    /// This writes the whole database with [Database::dump_db], which removes
+    /// the journal once the save is safely on disk.
+    pub fn compact(&self) -> Result<(), DatabaseError> {
+        self.dump_db()
+    }
+
+    /// Appends an inserted item to the journal, if [Database::journal] is
+    /// enabled.
+    pub(crate) fn journal_insert(&mut self, item: &Record) -> Result<(), DatabaseError> {
+        if !self.journal {
+            return Ok(());
+        }
+
+        self.append_journal(Entry::Insert(Value::serialize_from(item)?))
+    }
+
+    /// Appends a removed key to the journal, if [Database::journal] is
+    /// enabled.
+    pub(crate) fn journal_remove(&mut self, key: &Record::Key) -> Result<(), DatabaseError> {
+        if !self.journal {
+            return Ok(());
+        }
+
+        self.append_journal(Entry::Remove(Value::serialize_from(key)?))
+    }
+
+    /// Replays the journal next to the save at `path` on top of the database,
+    /// stopping at the first damaged entry. Returns if there was a journal,
+    /// missing journals are ignored.
+    pub(crate) fn replay_journal(&mut self, path: &Path) -> Result<bool, DatabaseError> {
+        let path = journal_path(path);
+
+        if !path.exists() {
+            return Ok(false);
+        }
+
+        let stream = std::fs::read(path)?;
+        let mut reader = &stream[..];
+
+        while let Some(frame) = read_frame(&mut reader) {
+            self.apply_entry(frame.schema_version, frame.entry)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Writes the entries buffered by a committed transaction to the journal
+    /// as a single entry.
+    pub(crate) fn write_journal(&self, entries: Vec<Entry>) -> Result<(), DatabaseError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        self.write_frame(Entry::Batch(entries))
+    }
+
+    /// Removes the journal next to the save, if there is one.
+    pub(crate) fn clear_journal(&self) -> Result<(), DatabaseError> {
+        let path = journal_path(&self.smart_path_get());
+
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies a replayed entry to the in-memory database.
+    fn apply_entry(&mut self, schema_version: u32, entry: Entry) -> Result<(), DatabaseError> {
+        match entry {
+            Entry::Insert(item) => {
+                let item = Self::migrate(schema_version, item);
+                let item: Record = item
+                    .deserialize_into()
+                    .map_err(|_| DatabaseError::CorruptFile)?;
+
+                self.put_item(item);
+            }
+            Entry::Remove(key) => {
+                let key: Record::Key = key
+                    .deserialize_into()
+                    .map_err(|_| DatabaseError::CorruptFile)?;
+
+                self.take_item(&key);
+            }
+            Entry::Batch(entries) => {
+                for entry in entries {
+                    self.apply_entry(schema_version, entry)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes an entry to the end of the journal, or buffers it until the
+    /// current transaction is committed.
+    fn append_journal(&mut self, entry: Entry) -> Result<(), DatabaseError> {
+        match self.transaction.as_mut() {
+            Some(transaction) => {
+                transaction.journal.push(entry);
+                Ok(())
+            }
+            None => self.write_frame(entry),
+        }
+    }
+
+    /// Writes an entry to the end of the journal file, waiting until it's
+    /// flushed to disk.
+    fn write_frame(&self, entry: Entry) -> Result<(), DatabaseError> {
+        let frame = Frame {
+            schema_version: Self::schema_version(),
+            entry,
+        };
+        let payload = bincode::serialize(&frame)?;
+
+        let mut stream = Vec::with_capacity(payload.len() + 8);
+        stream.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        stream.extend_from_slice(&crc32(&payload).to_le_bytes());
+        stream.extend_from_slice(&payload);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(journal_path(&self.smart_path_get()))?;
+
+        file.write_all(&stream)?;
+        file.sync_data()?;
+
+        Ok(())
+    }
+}
+
+/// Returns the path of the journal kept next to a save path.
+fn journal_path(path: &Path) -> PathBuf {
+    suffixed_path(path, "journal")
+}
+
+/// Reads the next frame from the front of `reader`, returning [Option::None]
+/// at the end of the journal or if the frame is incomplete or damaged.
+fn read_frame(reader: &mut &[u8]) -> Option<Frame> {
+    if reader.len() < 8 {
+        return None;
+    }
+
+    let len = u32::from_le_bytes(reader[0..4].try_into().ok()?) as usize;
+    let checksum = u32::from_le_bytes(reader[4..8].try_into().ok()?);
+    let payload = reader.get(8..8 + len)?;
+
+    if crc32(payload) != checksum {
+        return None;
+    }
+
+    *reader = &reader[8 + len..];
+    bincode::deserialize(payload).ok()
+}
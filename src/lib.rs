@@ -4,7 +4,7 @@
 //!
 //! This database aims to provide an easy frontend to an efficient in-memory database, that can be saved and reloaded.
 //!
-//! GDDB saves a Godot dictionary and provides an interface to create, update, retrieve (either single results or all items matching the search) and destroy records.
+//! GDDB saves a Godot dictionary as a structured [Value] and provides an interface to create, update, retrieve (either single results or all items matching the search) and destroy records.
 //!
 //! GDDB started as a fork of [TinyDB](https://github.com/Owez/tinydb) with added functionality and a Godot wrapper.
 //!
@@ -17,13 +17,21 @@
 //!
 //! ```rust
 //! use serde::{Serialize, Deserialize};
-//! use gddb::Database;
+//! use gddb::{Database, Keyed};
 //!
 //! #[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize, Clone)]
 //! struct PlayerStruct {
 //!     name: String
 //! }
 //!
+//! impl Keyed for PlayerStruct {
+//!     type Key = String;
+//!
+//!     fn primary_key(&self) -> String {
+//!         self.name.clone()
+//!     }
+//! }
+//!
 //! fn main() {
 //!     let player = PlayerStruct { name: "Joe Bloggs".into() };
 //!     let mut db = Database::new("GAME", None, false);
@@ -51,40 +59,102 @@
 //! - This project is not intended to be used inside of any critical systems due to
 //! the nature of dumping/recovery. If you are using this crate as a temporary and
 //! in-memory only database, it should preform at a reasonable speed (as it uses
-//! [HashSet] underneath).
+//! a [HashMap] keyed by [Keyed::primary_key] underneath).
 //!
 //! # Essential operations
 //!
 //! Some commonly-used operations for the [Database] structure.
 //!
-//! | Operation                               | Implamentation        |
-//! |-----------------------------------------|-----------------------|
-//! | Create database                         | [Database::new]       |
-//! | Create database from file               | [Database::from]      |
-//! | Load database or create if non-existant | [Database::auto_from] |
-//! | Query all matching items                | [Database::query]     |
-//! | Query for item                          | [Database::find]      |
-//! | Contains specific item                  | [Database::contains]  |
-//! | Update/replace item                     | [Database::update]    |
-//! | Delete item                             | [Database::destroy]   |
-//! | Dump database                           | [Database::dump_db]   |
+//! | Operation                               | Implamentation                 |
+//! |-----------------------------------------|--------------------------------|
+//! | Create database                         | [Database::new]                |
+//! | Create database from file               | [Database::from]               |
+//! | Create database from file in a format   | [Database::from_with_format]   |
+//! | Load database or create if non-existant | [Database::auto_from]          |
+//! | Query all matching items                | [Database::query]              |
+//! | Query for item                          | [Database::find]               |
+//! | Declare a secondary index               | [Database::create_index]       |
+//! | Query all items by secondary index      | [Database::query_index]        |
+//! | Query records with a query string       | [Database::where_query]        |
+//! | Get item by primary key                 | [Database::get]                |
+//! | Contains specific item                  | [Database::contains]           |
+//! | Update/replace item                     | [Database::update]             |
+//! | Change item in place                    | [Database::modify]             |
+//! | Add to a numeric attribute              | [Database::increment]          |
+//! | Insert or replace item by primary key   | [Database::upsert]             |
+//! | Delete item                             | [Database::destroy]            |
+//! | Delete item by primary key              | [Database::remove_by_key]      |
+//! | Delete every matching item              | [Database::destroy_where]      |
+//! | Apply several changes atomically        | [Database::transaction]        |
+//! | Find items in a range of values         | [Database::query_range]        |
+//! | Sort and page through query results     | [Database::select]             |
+//! | Sum, average or count items             | [Database::aggregate]          |
+//! | Search items by keywords                | [Database::search]             |
+//! | Keep several named collections          | [Catalog::create_collection]   |
+//! | Validate items against a schema         | [Database::define_model]       |
+//! | Keep a value unique across items        | [Database::create_unique]      |
+//! | Follow references between items         | [Database::related]            |
+//! | Be notified of every change             | [Database::observe]            |
+//! | Follow the results of a query           | [Database::subscribe]          |
+//! | Undo the most recent change             | [Database::undo]               |
+//! | Dump database                           | [Database::dump_db]            |
+//! | Fold the journal into the save          | [Database::compact]            |
+//! | Dump database in another format         | [Database::dump_db_as]         |
+//! | Export a snapshot to another path       | [Database::export_db]          |
+//! | Upgrade items from older saves          | [Database::register_migration] |
 
+pub mod aggregate;
+pub mod atomic;
+pub mod catalog;
+pub mod convert;
 pub mod database;
 pub mod error;
+pub mod format;
 pub mod gddb;
+pub mod header;
+pub mod history;
+pub mod index;
+pub mod journal;
+pub mod migration;
+pub mod observer;
+pub mod query;
 pub mod record;
+pub mod relation;
+pub mod schema;
+pub mod search;
+pub mod select;
+pub mod subscription;
+pub mod transaction;
+pub mod unique;
+pub mod value;
 use gdnative::prelude::*;
 
 mod prelude {
+    pub use crate::aggregate::*;
+    pub use crate::catalog::*;
+    pub use crate::convert::*;
     pub use crate::database::*;
     pub use crate::error::*;
+    pub use crate::format::*;
     pub use crate::gddb::*;
+    pub use crate::header::*;
+    pub use crate::index::*;
+    pub use crate::observer::*;
+    pub use crate::query::*;
     pub use crate::record::*;
+    pub use crate::relation::*;
+    pub use crate::schema::*;
+    pub use crate::search::*;
+    pub use crate::select::*;
+    pub use crate::subscription::*;
+    pub use crate::unique::*;
+    pub use crate::value::*;
 
     pub use core::fmt::Display;
     pub use gdnative::prelude::*;
-    pub use hashbrown::HashSet;
+    pub use hashbrown::{HashMap, HashSet};
     pub use serde::{de::DeserializeOwned, Deserialize, Serialize};
+    pub use std::borrow::Borrow;
     pub use std::fs::File;
     pub use std::hash;
     pub use std::io::prelude::*;
@@ -96,6 +166,8 @@ use prelude::*;
 
 fn init(handle: InitHandle) {
     handle.add_class::<GDDB>();
+    handle.add_class::<LiveQuery>();
+    handle.add_class::<Collection>();
 }
 
 godot_init!(init);
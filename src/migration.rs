@@ -0,0 +1,93 @@
+//! Schema migrations, upgrading items saved by an older version of a game
+//! when they are loaded with [Database::from].
+//!
+//! Every item type has a schema version, which starts at `0` and is one more
+//! than the highest version a migration is registered for. Saves remember the
+//! schema version they were written with, and when loaded each item is passed
+//! through every migration from that version up to the current one.
+//!
+//! ```rust
+//! use gddb::{Database, Record, Value};
+//!
+//! /// Version 0 of `Enemy` records stored `health`, version 1 renames it `hp`.
+//! fn rename_health(_version: u32, mut record: Value) -> Value {
+//!     if let Some(Value::Map(attributes)) = record.get_mut("attributes") {
+//!         if let Some(health) = attributes.remove("health") {
+//!             attributes.insert("hp".into(), health);
+//!         }
+//!     }
+//!
+//!     record
+//! }
+//!
+//! fn main() {
+//!     Database::<Record>::register_migration(0, rename_health);
+//!
+//!     assert_eq!(Database::<Record>::schema_version(), 1);
+//! }
+//! ```
+
+use crate::prelude::*;
+use std::sync::Mutex;
+
+/// Upgrades an item from the schema version it is given to the next version.
+///
+/// Items are given in their [Value] form, the same shape [serde_json] would
+/// give them, so a [Record] is a map with `uuid`, `model` and `attributes`.
+pub type Migration = fn(u32, Value) -> Value;
+
+/// Every registered migration, along with the [Keyed::type_name] and schema
+/// version it upgrades from.
+static MIGRATIONS: Mutex<Vec<(&'static str, u32, Migration)>> = Mutex::new(Vec::new());
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
+    /// Registers a migration upgrading items from schema `version` to
+    /// `version + 1`, replacing any migration already registered for that
+    /// version.
+    ///
+    /// Migrations must be registered before loading a save with
+    /// [Database::from] or [Database::auto_from], and are shared by every
+    /// database storing the same type.
+    pub fn register_migration(version: u32, step: Migration) {
+        let mut migrations = MIGRATIONS.lock().unwrap_or_else(|e| e.into_inner());
+        let type_name = Record::type_name();
+
+        migrations.retain(|(name, v, _)| !(*name == type_name && *v == version));
+        migrations.push((type_name, version, step));
+    }
+
+    /// The schema version items are saved with by [Database::dump_db], which
+    /// is one more than the highest version a migration is registered for,
+    /// or `0` if there are none.
+    pub fn schema_version() -> u32 {
+        MIGRATIONS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|(name, _, _)| *name == Record::type_name())
+            .map(|(_, version, _)| version + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Upgrades an item saved with schema version `from` to the current
+    /// [Database::schema_version]. Versions without a registered migration
+    /// leave the item unchanged.
+    pub(crate) fn migrate(from: u32, mut item: Value) -> Value {
+        let mut steps: Vec<(u32, Migration)> = MIGRATIONS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|(name, version, _)| *name == Record::type_name() && *version >= from)
+            .map(|(_, version, step)| (*version, *step))
+            .collect();
+
+        steps.sort_by_key(|(version, _)| *version);
+
+        for (version, step) in steps {
+            item = step(version, item);
+        }
+
+        item
+    }
+}
@@ -0,0 +1,211 @@
+//! Observers, notified of every change made to a [Database] so games don't
+//! have to poll for them.
+//!
+//! Observers are called after each change is made, or once a transaction is
+//! committed for changes made inside of it. Changes that are rolled back are
+//! never observed.
+//!
+//! ```rust
+//! use gddb::{Change, Database, Record};
+//! use std::sync::{Arc, Mutex};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("GAME", None, false);
+//!     let created = Arc::new(Mutex::new(0));
+//!
+//!     let counter = created.clone();
+//!     db.observe(move |change: &Change<Record>| {
+//!         if let Change::Created(_) = change {
+//!             *counter.lock().unwrap() += 1;
+//!         }
+//!     });
+//!
+//!     db.create(Record::new("Player".into())).unwrap();
+//!     assert_eq!(*created.lock().unwrap(), 1);
+//! }
+//! ```
+
+use crate::history::Edit;
+use crate::prelude::*;
+use std::fmt;
+
+/// A change made to an item inside of a [Database], given to observers
+/// registered with [Database::observe].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<T> {
+    /// A new item was added.
+    Created(T),
+
+    /// An item was replaced, with its value before and after.
+    Updated { old: T, new: T },
+
+    /// An item was removed.
+    Destroyed(T),
+}
+
+impl<T> Change<T> {
+    /// The item after the change, or the removed item for
+    /// [Change::Destroyed].
+    pub fn item(&self) -> &T {
+        match self {
+            Change::Created(item) => item,
+            Change::Updated { new, .. } => new,
+            Change::Destroyed(item) => item,
+        }
+    }
+
+    /// Converts a before and after state into a change, returning
+    /// [Option::None] if the item neither existed before nor after.
+    pub(crate) fn from_edit<K>(edit: &Edit<K, T>) -> Option<Self>
+    where
+        T: Clone,
+    {
+        match (&edit.before, &edit.after) {
+            (None, Some(new)) => Some(Change::Created(new.clone())),
+            (Some(old), Some(new)) => Some(Change::Updated {
+                old: old.clone(),
+                new: new.clone(),
+            }),
+            (Some(old), None) => Some(Change::Destroyed(old.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Identifies an observer registered with [Database::observe], so it can be
+/// removed with [Database::unobserve].
+pub type ObserverId = usize;
+
+/// A callback registered with [Database::observe].
+type Observer<T> = Box<dyn FnMut(&Change<T>) + Send>;
+
+/// The observers registered on a [Database].
+///
+/// Observers can't be cloned or compared, so cloning a database leaves the
+/// clone without observers and they are ignored when comparing databases.
+pub(crate) struct Observers<T> {
+    next_id: ObserverId,
+    callbacks: Vec<(ObserverId, Observer<T>)>,
+}
+
+impl<T> Observers<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            next_id: 0,
+            callbacks: Vec::new(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+}
+
+impl<T> Clone for Observers<T> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PartialEq for Observers<T> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> fmt::Debug for Observers<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Observers({})", self.callbacks.len())
+    }
+}
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
+    /// Registers a callback to be called with every [Change] made to the
+    /// database, returning an id to remove it with [Database::unobserve].
+    ///
+    /// Observers aren't saved, cloned or compared along with the database.
+    pub fn observe(
+        &mut self,
+        observer: impl FnMut(&Change<Record>) + Send + 'static,
+    ) -> ObserverId {
+        let id = self.observers.next_id;
+
+        self.observers.next_id += 1;
+        self.observers.callbacks.push((id, Box::new(observer)));
+
+        id
+    }
+
+    /// Removes an observer registered with [Database::observe], returning if
+    /// it was registered.
+    pub fn unobserve(&mut self, id: ObserverId) -> bool {
+        let len = self.observers.callbacks.len();
+
+        self.observers.callbacks.retain(|(other, _)| *other != id);
+        self.observers.callbacks.len() != len
+    }
+
+    /// Calls every observer with the changes made by some edits.
+    pub(crate) fn notify(&mut self, edits: &[Edit<Record::Key, Record>]) {
+        if self.observers.is_empty() {
+            return;
+        }
+
+        for change in edits.iter().filter_map(Change::from_edit) {
+            for (_, observer) in self.observers.callbacks.iter_mut() {
+                observer(&change);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Tests observers see every committed change, and nothing that was
+    /// rolled back
+    #[test]
+    fn observe_changes() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Observers test", None, false);
+        let changes = Arc::new(Mutex::new(Vec::new()));
+
+        let seen = changes.clone();
+        let id = db.observe(move |change: &Change<Record>| {
+            seen.lock().unwrap().push(change.clone());
+        });
+
+        let record = Record::new("Player".into());
+        let mut updated = record.clone();
+        updated.set("hp", 10);
+
+        db.create(record.clone())?;
+        db.update(&record, updated.clone())?;
+        let _ = db.transaction(|db| {
+            db.create(Record::new("Enemy".into()))?;
+            Err::<(), _>(DatabaseError::ItemNotFound)
+        });
+        db.destroy(&updated)?;
+
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![
+                Change::Created(record.clone()),
+                Change::Updated {
+                    old: record,
+                    new: updated.clone(),
+                },
+                Change::Destroyed(updated),
+            ]
+        );
+
+        assert!(db.unobserve(id));
+        assert!(!db.unobserve(id));
+
+        db.create(Record::new("Player".into()))?;
+        assert_eq!(changes.lock().unwrap().len(), 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,587 @@
+//! A small query language for filtering [Record] databases by their fields and
+//! attributes.
+//!
+//! # Syntax
+//!
+//! ```none
+//! model == "Enemy" and attributes.hp < 10
+//! not (attributes.faction in ["pirates", "smugglers"]) or exists attributes.boss
+//! attributes.tags contains "fire"
+//! ```
+//!
+//! - Paths are `model`, `uuid` or `attributes.[path]`, where `[path]` is passed
+//!   to [Value::get_path]. Any other path is assumed to be an attribute path,
+//!   so `hp < 10` is the same as `attributes.hp < 10`.
+//! - Comparisons are `==`, `!=`, `<`, `<=`, `>`, `>=`, `in` (against an array
+//!   literal) and `contains` (substring, array element or map key).
+//! - Literals are strings in single or double quotes, numbers, `true`, `false`,
+//!   `null` and arrays of literals like `[1, 2]`.
+//! - Conditions are combined with `and`, `or` and `not` with the usual
+//!   precedence, and grouped with parentheses. `exists [path]` checks that a
+//!   path is present.
+//!
+//! Missing paths compare as `null`.
+
+use crate::prelude::*;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+/// A comparison operator inside of a [Query].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    Contains,
+}
+
+/// A parsed query over [Record]s, see the [module docs](self) for its syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// Compares the value at a path with a literal.
+    Compare(String, Comparison, Value),
+    /// Checks that a path is present.
+    Exists(String),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+}
+
+impl Query {
+    /// Parses a query string.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::BadQuery] describing the problem if the
+    /// query is not valid.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::{Query, Record};
+    ///
+    /// fn main() {
+    ///     let query = Query::parse(r#"model == "Enemy" and attributes.hp < 10"#).unwrap();
+    ///
+    ///     let mut enemy = Record::new("Enemy".into());
+    ///     enemy.set("hp", 5);
+    ///
+    ///     assert!(query.matches(&enemy));
+    /// }
+    /// ```
+    pub fn parse(query: &str) -> Result<Self, DatabaseError> {
+        let mut parser = Parser {
+            tokens: tokenize(query)?,
+            pos: 0,
+        };
+
+        let parsed = parser.or()?;
+
+        match parser.next() {
+            None => Ok(parsed),
+            Some(token) => Err(DatabaseError::BadQuery(format!(
+                "unexpected {:?} after end of query",
+                token
+            ))),
+        }
+    }
+
+    /// Returns `true` if the record is matched by this query.
+    pub fn matches(&self, record: &Record) -> bool {
+        match self {
+            Query::Compare(path, op, literal) => compare(
+                &resolve(record, path).unwrap_or(Cow::Owned(Value::Null)),
+                *op,
+                literal,
+            ),
+            Query::Exists(path) => resolve(record, path).is_some(),
+            Query::And(a, b) => a.matches(record) && b.matches(record),
+            Query::Or(a, b) => a.matches(record) || b.matches(record),
+            Query::Not(q) => !q.matches(record),
+        }
+    }
+
+    /// Returns the model this query is restricted to, if it requires
+    /// `model == "..."` to match. Used to narrow candidates with the `by_model`
+    /// index.
+    pub fn model(&self) -> Option<&str> {
+        match self {
+            Query::Compare(path, Comparison::Eq, Value::String(model)) if path == "model" => {
+                Some(model)
+            }
+            Query::And(a, b) => a.model().or_else(|| b.model()),
+            _ => None,
+        }
+    }
+}
+
+impl Database<Record> {
+    /// Returns all records matched by a [Query].
+    ///
+    /// Queries requiring a specific `model` only look at records of that model
+    /// using the `by_model` index, rather than scanning the database.
+    pub fn filter(&self, query: &Query) -> Vec<&Record> {
+        let candidates: Vec<&Record> = match query.model() {
+            Some(model) => match self.query_index("by_model", model) {
+                Ok(records) => records,
+                Err(DatabaseError::ItemNotFound) => vec![],
+                Err(_) => self.items.values().collect(),
+            },
+            None => self.items.values().collect(),
+        };
+
+        candidates
+            .into_iter()
+            .filter(|record| query.matches(record))
+            .collect()
+    }
+
+    /// Parses a query string and returns all records matched by it, see
+    /// [Query::parse] and [Database::filter].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::{Database, Record};
+    ///
+    /// fn main() {
+    ///     let mut db: Database<Record> = Database::new("GAME", None, false);
+    ///
+    ///     let mut enemy = Record::new("Enemy".into());
+    ///     enemy.set("hp", 5);
+    ///     db.create(enemy).unwrap();
+    ///
+    ///     let results = db.where_query(r#"model == "Enemy" and attributes.hp < 10"#);
+    ///
+    ///     assert_eq!(results.unwrap().len(), 1);
+    /// }
+    /// ```
+    pub fn where_query(&self, query: &str) -> Result<Vec<&Record>, DatabaseError> {
+        Ok(self.filter(&Query::parse(query)?))
+    }
+}
+
+/// Resolves a query path against a record.
+pub(crate) fn resolve<'a>(record: &'a Record, path: &str) -> Option<Cow<'a, Value>> {
+    match path {
+        "model" => Some(Cow::Owned(Value::from(record.model.as_str()))),
+        "uuid" => Some(Cow::Owned(Value::from(record.uuid.as_str()))),
+        "attributes" => Some(Cow::Borrowed(&record.attributes)),
+        _ => path
+            .strip_prefix("attributes.")
+            .map_or_else(|| record.get_path(path), |rest| record.get_path(rest))
+            .map(Cow::Borrowed),
+    }
+}
+
+/// Compares a resolved value against a literal.
+fn compare(value: &Value, op: Comparison, literal: &Value) -> bool {
+    match op {
+        Comparison::Eq => values_eq(value, literal),
+        Comparison::Ne => !values_eq(value, literal),
+        Comparison::Lt => values_cmp(value, literal) == Some(Ordering::Less),
+        Comparison::Le => matches!(
+            values_cmp(value, literal),
+            Some(Ordering::Less | Ordering::Equal)
+        ),
+        Comparison::Gt => values_cmp(value, literal) == Some(Ordering::Greater),
+        Comparison::Ge => matches!(
+            values_cmp(value, literal),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+        Comparison::In => match literal {
+            Value::Array(items) => items.iter().any(|item| values_eq(value, item)),
+            _ => false,
+        },
+        Comparison::Contains => match (value, literal) {
+            (Value::String(s), Value::String(sub)) => s.contains(sub.as_str()),
+            (Value::Array(items), _) => items.iter().any(|item| values_eq(item, literal)),
+            (Value::Map(map), Value::String(key)) => map.contains_key(key),
+            _ => false,
+        },
+    }
+}
+
+/// Equality where integers and floats compare exactly by their numeric
+/// value, agreeing with [Value::total_cmp].
+fn values_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => {
+            a.total_cmp(b).is_eq()
+        }
+        _ => a == b,
+    }
+}
+
+/// Orders two numbers or two strings by [Value::total_cmp], anything else
+/// including `NaN` is unordered.
+fn values_cmp(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ if a.as_f64()?.is_nan() || b.as_f64()?.is_nan() => None,
+        _ => Some(a.total_cmp(b)),
+    }
+}
+
+/// A lexical token of a query string.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Literal(Value),
+    Op(Comparison),
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+}
+
+/// Splits a query string into [Token]s.
+fn tokenize(query: &str) -> Result<Vec<Token>, DatabaseError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let two: String = chars[i..chars.len().min(i + 2)].iter().collect();
+        let op = match two.as_str() {
+            "==" => Some((Comparison::Eq, 2)),
+            "!=" => Some((Comparison::Ne, 2)),
+            "<=" => Some((Comparison::Le, 2)),
+            ">=" => Some((Comparison::Ge, 2)),
+            _ if c == '<' => Some((Comparison::Lt, 1)),
+            _ if c == '>' => Some((Comparison::Gt, 1)),
+            _ => None,
+        };
+
+        if let Some((op, len)) = op {
+            tokens.push(Token::Op(op));
+            i += len;
+            continue;
+        }
+
+        match c {
+            '(' => tokens.push(Token::OpenParen),
+            ')' => tokens.push(Token::CloseParen),
+            '[' => tokens.push(Token::OpenBracket),
+            ']' => tokens.push(Token::CloseBracket),
+            ',' => tokens.push(Token::Comma),
+            '"' | '\'' => {
+                let mut s = String::new();
+                i += 1;
+
+                loop {
+                    match chars.get(i) {
+                        None => return Err(DatabaseError::BadQuery("unterminated string".into())),
+                        Some(&end) if end == c => break,
+                        Some('\\') => {
+                            i += 1;
+                            match chars.get(i) {
+                                Some('n') => s.push('\n'),
+                                Some('t') => s.push('\t'),
+                                Some(&escaped) => s.push(escaped),
+                                None => {
+                                    return Err(DatabaseError::BadQuery(
+                                        "unterminated string".into(),
+                                    ))
+                                }
+                            }
+                        }
+                        Some(&ch) => s.push(ch),
+                    }
+                    i += 1;
+                }
+
+                tokens.push(Token::Literal(Value::String(s)));
+            }
+            _ if c.is_ascii_digit() || (c == '-' && next_is_digit(&chars, i)) => {
+                let start = i;
+                i += 1;
+
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+
+                let number: String = chars[start..i].iter().collect();
+                let value = match number.parse::<i64>() {
+                    Ok(int) => Value::Int(int),
+                    Err(_) => Value::Float(number.parse::<f64>().map_err(|_| {
+                        DatabaseError::BadQuery(format!("invalid number {}", number))
+                    })?),
+                };
+
+                tokens.push(Token::Literal(value));
+                continue;
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+                {
+                    i += 1;
+                }
+
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.as_str() {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "null" => Token::Literal(Value::Null),
+                    "in" => Token::Op(Comparison::In),
+                    "contains" => Token::Op(Comparison::Contains),
+                    _ => Token::Word(word),
+                });
+                continue;
+            }
+            _ => {
+                return Err(DatabaseError::BadQuery(format!(
+                    "unexpected character '{}'",
+                    c
+                )))
+            }
+        }
+
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+/// Returns `true` if the character after `i` is a digit.
+fn next_is_digit(chars: &[char], i: usize) -> bool {
+    matches!(chars.get(i + 1), Some(c) if c.is_ascii_digit())
+}
+
+/// Recursive descent parser over [Token]s.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Consumes the next token if it is the given keyword.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Word(word)) if word == keyword) {
+            self.pos += 1;
+            return true;
+        }
+
+        false
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), DatabaseError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(DatabaseError::BadQuery(format!(
+                "expected {:?}, found {:?}",
+                expected, other
+            ))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Query, DatabaseError> {
+        let mut query = self.and()?;
+
+        while self.keyword("or") {
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query, DatabaseError> {
+        let mut query = self.not()?;
+
+        while self.keyword("and") {
+            query = Query::And(Box::new(query), Box::new(self.not()?));
+        }
+
+        Ok(query)
+    }
+
+    fn not(&mut self) -> Result<Query, DatabaseError> {
+        if self.keyword("not") {
+            return Ok(Query::Not(Box::new(self.not()?)));
+        }
+
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<Query, DatabaseError> {
+        if self.peek() == Some(&Token::OpenParen) {
+            self.pos += 1;
+            let query = self.or()?;
+            self.expect(Token::CloseParen)?;
+
+            return Ok(query);
+        }
+
+        if self.keyword("exists") {
+            return Ok(Query::Exists(self.path()?));
+        }
+
+        let path = self.path()?;
+
+        match self.next() {
+            Some(Token::Op(op)) => Ok(Query::Compare(path, op, self.literal()?)),
+            other => Err(DatabaseError::BadQuery(format!(
+                "expected comparison after {}, found {:?}",
+                path, other
+            ))),
+        }
+    }
+
+    fn path(&mut self) -> Result<String, DatabaseError> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            other => Err(DatabaseError::BadQuery(format!(
+                "expected path, found {:?}",
+                other
+            ))),
+        }
+    }
+
+    fn literal(&mut self) -> Result<Value, DatabaseError> {
+        match self.next() {
+            Some(Token::Literal(value)) => Ok(value),
+            Some(Token::OpenBracket) => {
+                let mut items = vec![];
+
+                if self.peek() == Some(&Token::CloseBracket) {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+
+                loop {
+                    items.push(self.literal()?);
+
+                    match self.next() {
+                        Some(Token::Comma) => continue,
+                        Some(Token::CloseBracket) => return Ok(Value::Array(items)),
+                        other => {
+                            return Err(DatabaseError::BadQuery(format!(
+                                "expected ',' or ']', found {:?}",
+                                other
+                            )))
+                        }
+                    }
+                }
+            }
+            other => Err(DatabaseError::BadQuery(format!(
+                "expected value, found {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enemy(hp: i64, tags: Vec<&str>) -> Record {
+        let mut record = Record::new("Enemy".into());
+        record.set("hp", hp);
+        record.set("tags", tags);
+        record
+    }
+
+    /// Tests parsing and matching each kind of condition
+    #[test]
+    fn query_matches() -> Result<(), DatabaseError> {
+        let record = enemy(5, vec!["fire", "boss"]);
+
+        for query in [
+            r#"model == "Enemy""#,
+            "attributes.hp < 10",
+            "hp >= 5.0",
+            "hp in [1, 5, 9]",
+            "attributes.tags contains 'fire'",
+            "exists attributes.tags",
+            "not exists attributes.mana",
+            "attributes.mana == null",
+            r#"model == "Player" or (hp > 1 and hp != 4)"#,
+        ] {
+            assert!(Query::parse(query)?.matches(&record), "{}", query);
+        }
+
+        for query in [
+            "model == 'Player'",
+            "hp > 5",
+            "attributes.tags contains 'ice'",
+            "not model contains 'Ene'",
+        ] {
+            assert!(!Query::parse(query)?.matches(&record), "{}", query);
+        }
+
+        Ok(())
+    }
+
+    /// Tests integers and floats compare exactly, beyond the precision of a
+    /// float
+    #[test]
+    fn exact_numbers() -> Result<(), DatabaseError> {
+        let mut record = Record::new("Item".into());
+        record.set("id", (1u64 << 53) as f64);
+        record.set("nan", f64::NAN);
+
+        for (query, matches) in [
+            ("id == 9007199254740992", true),
+            ("id == 9007199254740993", false),
+            ("id != 9007199254740993", true),
+            ("id < 9007199254740993", true),
+            ("id >= 9007199254740993", false),
+            ("nan < 1", false),
+            ("nan >= 1", false),
+        ] {
+            assert_eq!(Query::parse(query)?.matches(&record), matches, "{}", query);
+        }
+
+        Ok(())
+    }
+
+    /// Tests invalid queries are rejected
+    #[test]
+    fn query_errors() {
+        for query in ["", "hp <", "hp < 10 and", "(hp < 10", "hp ~ 1", "'open"] {
+            assert!(Query::parse(query).is_err(), "{}", query);
+        }
+    }
+
+    /// Tests [Database::where_query]
+    #[test]
+    fn where_query() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Where test", None, false);
+
+        db.create(enemy(5, vec![]))?;
+        db.create(enemy(50, vec![]))?;
+        db.create(Record::new("Player".into()))?;
+
+        assert_eq!(db.where_query("model == 'Enemy' and hp < 10")?.len(), 1);
+        assert_eq!(db.where_query("model == 'Quest'")?.len(), 0);
+        assert_eq!(db.where_query("not exists hp")?.len(), 1);
+
+        Ok(())
+    }
+}
@@ -1,13 +1,19 @@
 use crate::prelude::*;
+use std::collections::BTreeMap;
 
 pub trait RecordCheck: PartialEq + Default + Display {}
 impl<T> RecordCheck for T where T: PartialEq + Default + Display {}
 
-#[derive(Clone, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Record {
     pub uuid: String,
     pub model: String,
-    pub attributes: String,
+    pub attributes: Value,
+
+    /// References to other records by the name of each relation, see
+    /// [crate::relation].
+    #[serde(default)]
+    pub links: BTreeMap<String, RecordRef>,
 }
 
 impl Record {
@@ -17,7 +23,83 @@ impl Record {
         Self {
             uuid,
             model,
-            attributes: "".into(),
+            attributes: Value::map(),
+            links: BTreeMap::new(),
         }
     }
+
+    /// Gets a top-level attribute.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.attributes.get(key)
+    }
+
+    /// Sets a top-level attribute, returning its previous value.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<Value>) -> Option<Value> {
+        self.attributes.insert(key, value)
+    }
+
+    /// Gets a nested attribute from a `.` separated path, see [Value::get_path].
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        self.attributes.get_path(path)
+    }
+
+    /// Refers to another record through a relation, returning the reference
+    /// it replaced.
+    pub fn link(&mut self, relation: impl Into<String>, target: &Record) -> Option<RecordRef> {
+        let reference = RecordRef {
+            model: target.model.clone(),
+            uuid: target.uuid.clone(),
+        };
+
+        self.links.insert(relation.into(), reference)
+    }
+
+    /// Removes the reference through a relation, returning it.
+    pub fn unlink(&mut self, relation: &str) -> Option<RecordRef> {
+        self.links.remove(relation)
+    }
+
+    /// Gets the reference through a relation.
+    pub fn linked(&self, relation: &str) -> Option<&RecordRef> {
+        self.links.get(relation)
+    }
+}
+
+impl Keyed for Record {
+    type Key = String;
+
+    fn primary_key(&self) -> String {
+        self.uuid.clone()
+    }
+
+    fn type_name() -> &'static str {
+        "Record"
+    }
+
+    fn indexes() -> Vec<(&'static str, IndexFn<Self>)> {
+        vec![("by_model", |r| r.model.clone())]
+    }
+
+    fn validate(&mut self, schemas: &HashMap<String, Schema>) -> Result<(), DatabaseError> {
+        match schemas.get(&self.model) {
+            Some(schema) => schema.validate(&mut self.attributes),
+            None => Ok(()),
+        }
+    }
+
+    fn references(&self) -> Vec<(String, String)> {
+        self.links
+            .iter()
+            .map(|(relation, link)| (relation.clone(), link.uuid.clone()))
+            .collect()
+    }
+
+    fn clear_reference(&mut self, relation: &str) {
+        self.links.remove(relation);
+    }
+
+    fn refers_to(&self, relation: &str, target: &Record) -> bool {
+        self.linked(relation)
+            .is_some_and(|link| link.model == target.model)
+    }
 }
@@ -0,0 +1,449 @@
+//! Relations between items, such as an item belonging to an inventory that
+//! belongs to a player.
+//!
+//! A [Record] refers to another with [Record::link], storing a [RecordRef]
+//! under the name of the relation, and each record can have one reference per
+//! relation. The database keeps a reverse index of every reference, so
+//! [Database::related] follows a reference and [Database::referrers] finds
+//! every record referring to another without scanning the database.
+//!
+//! References are checked whenever a record is written: creating or updating
+//! a record referring to a record that doesn't exist, or one of a different
+//! model than its [RecordRef], fails with [DatabaseError::BrokenReference],
+//! and the uuid of a record others refer to can't be changed.
+//!
+//! Destroying a record that others refer to follows the [OnDestroy] rule of
+//! each relation, set with [Database::on_destroy]. By default relations
+//! [OnDestroy::Restrict] the destroy, so references never point at records
+//! that were destroyed.
+//!
+//! ```rust
+//! use gddb::{Database, OnDestroy, Record};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("GAME", None, false);
+//!     db.on_destroy("owner", OnDestroy::Cascade);
+//!
+//!     let player = Record::new("Player".into());
+//!     let mut sword = Record::new("Item".into());
+//!     sword.link("owner", &player);
+//!
+//!     db.create(player.clone()).unwrap();
+//!     db.create(sword.clone()).unwrap();
+//!
+//!     assert_eq!(db.related(&sword.uuid, "owner").unwrap(), &player);
+//!     assert_eq!(db.referrers(&player.uuid, "owner"), vec![&sword]);
+//!
+//!     db.destroy(&player).unwrap();
+//!     assert_eq!(db.len(), 0);
+//! }
+//! ```
+
+use crate::prelude::*;
+
+/// A typed reference to another [Record], see [Record::link].
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct RecordRef {
+    /// The model of the record referred to.
+    pub model: String,
+
+    /// The uuid of the record referred to.
+    pub uuid: String,
+}
+
+/// What happens to the items referring to an item when it's destroyed, see
+/// [Database::on_destroy].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnDestroy {
+    /// The items referring to it are destroyed too, along with the items
+    /// referring to them.
+    Cascade,
+
+    /// The references to it are removed from the items referring to it.
+    Nullify,
+
+    /// The destroy fails with [DatabaseError::RelationRestricted].
+    #[default]
+    Restrict,
+}
+
+impl OnDestroy {
+    /// Parses a rule from `cascade`, `nullify` or `restrict`.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::BadQuery] for any other string.
+    pub fn parse(rule: &str) -> Result<Self, DatabaseError> {
+        match rule.trim().to_ascii_lowercase().as_str() {
+            "cascade" => Ok(OnDestroy::Cascade),
+            "nullify" => Ok(OnDestroy::Nullify),
+            "restrict" => Ok(OnDestroy::Restrict),
+            _ => Err(DatabaseError::BadQuery(format!(
+                "invalid on destroy rule {:?}",
+                rule
+            ))),
+        }
+    }
+}
+
+/// A reverse index from each item to the items referring to it, by the name
+/// of the relation, built from [Keyed::references].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct References<T: Keyed> {
+    referrers: HashMap<T::Key, HashMap<String, HashSet<T::Key>>>,
+}
+
+impl<T: Keyed> References<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            referrers: HashMap::new(),
+        }
+    }
+
+    /// Adds the references of an item to the index.
+    pub(crate) fn insert(&mut self, item: &T) {
+        for (relation, target) in item.references() {
+            self.referrers
+                .entry(target)
+                .or_default()
+                .entry(relation)
+                .or_default()
+                .insert(item.primary_key());
+        }
+    }
+
+    /// Removes the references of an item from the index.
+    pub(crate) fn remove(&mut self, item: &T) {
+        let key = item.primary_key();
+
+        for (relation, target) in item.references() {
+            let relations = match self.referrers.get_mut(&target) {
+                Some(relations) => relations,
+                None => continue,
+            };
+
+            if let Some(keys) = relations.get_mut(&relation) {
+                keys.remove(&key);
+
+                if keys.is_empty() {
+                    relations.remove(&relation);
+                }
+            }
+
+            if relations.is_empty() {
+                self.referrers.remove(&target);
+            }
+        }
+    }
+
+    /// Returns the keys of the items referring to an item, by relation.
+    fn get(&self, key: &T::Key) -> Option<&HashMap<String, HashSet<T::Key>>> {
+        self.referrers.get(key)
+    }
+}
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
+    /// Sets what happens to the items referring to an item through a relation
+    /// when it's destroyed, see [crate::relation].
+    ///
+    /// Like indexes, rules must be set again after loading.
+    pub fn on_destroy(&mut self, relation: impl Into<String>, rule: OnDestroy) {
+        self.relation_rules.insert(relation.into(), rule);
+    }
+
+    /// Returns the item an item refers to through a relation.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ItemNotFound] if either item doesn't
+    /// exist, or the item doesn't refer to anything through the relation, and
+    /// [DatabaseError::BrokenReference] if the item referred to isn't what
+    /// the relation expects, such as a record whose model has changed.
+    pub fn related<Q>(&self, key: &Q, relation: &str) -> Result<&Record, DatabaseError>
+    where
+        Record::Key: Borrow<Q>,
+        Q: hash::Hash + Eq + ?Sized,
+    {
+        let item = self.get(key)?;
+        let (_, target) = item
+            .references()
+            .into_iter()
+            .find(|(name, _)| name == relation)
+            .ok_or(DatabaseError::ItemNotFound)?;
+
+        let target = self
+            .items
+            .get::<Record::Key>(&target)
+            .ok_or(DatabaseError::ItemNotFound)?;
+
+        if !item.refers_to(relation, target) {
+            return Err(DatabaseError::BrokenReference(relation.into()));
+        }
+
+        Ok(target)
+    }
+
+    /// Returns every item referring to an item through a relation, using the
+    /// reverse index kept of every reference.
+    pub fn referrers(&self, key: &Record::Key, relation: &str) -> Vec<&Record> {
+        self.references
+            .get(key)
+            .and_then(|relations| relations.get(relation))
+            .into_iter()
+            .flatten()
+            .filter_map(|key| self.items.get(key))
+            .collect()
+    }
+
+    /// Checks every item an item refers to exists and is what its relation
+    /// expects, see [Keyed::refers_to]. Items may refer to themselves.
+    pub(crate) fn check_references(&self, item: &Record) -> Result<(), DatabaseError> {
+        let key = item.primary_key();
+
+        for (relation, target) in item.references() {
+            let valid = match self.items.get(&target) {
+                Some(target) => item.refers_to(&relation, target),
+                None => target == key && item.refers_to(&relation, item),
+            };
+
+            if !valid {
+                return Err(DatabaseError::BrokenReference(relation));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks no items refer to an item, before its key is changed.
+    pub(crate) fn check_referrers(&self, key: &Record::Key) -> Result<(), DatabaseError> {
+        match self
+            .references
+            .get(key)
+            .and_then(|relations| relations.keys().next())
+        {
+            Some(relation) => Err(DatabaseError::RelationRestricted(relation.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes an item as a single transaction, following the [OnDestroy]
+    /// rule of each relation referring to it. Returns the item if it existed.
+    pub(crate) fn destroy_item<Q>(&mut self, key: &Q) -> Result<Option<Record>, DatabaseError>
+    where
+        Record::Key: Borrow<Q>,
+        Q: hash::Hash + Eq + ?Sized,
+    {
+        let key = match self.items.get_key_value(key) {
+            Some((key, _)) => key.clone(),
+            None => return Ok(None),
+        };
+
+        self.transaction(|db| db.destroy_referred(&key))
+    }
+
+    /// Removes an item and then handles the items referring to it, so cycles
+    /// of references can't be followed forever.
+    fn destroy_referred(&mut self, key: &Record::Key) -> Result<Option<Record>, DatabaseError> {
+        let removed = match self.remove_item(key)? {
+            Some(removed) => removed,
+            None => return Ok(None),
+        };
+
+        let referrers = self.references.get(key).cloned().unwrap_or_default();
+
+        for (relation, keys) in referrers {
+            let rule = self
+                .relation_rules
+                .get(&relation)
+                .copied()
+                .unwrap_or_default();
+
+            match rule {
+                OnDestroy::Restrict => return Err(DatabaseError::RelationRestricted(relation)),
+                OnDestroy::Cascade => {
+                    for key in keys {
+                        self.destroy_referred(&key)?;
+                    }
+                }
+                OnDestroy::Nullify => {
+                    for key in keys {
+                        if let Some(mut item) = self.items.get(&key).cloned() {
+                            item.clear_reference(&relation);
+                            self.insert_item(item)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Some(removed))
+    }
+}
+
+impl Database<Record> {
+    /// Makes a record refer to another through a relation, replacing any
+    /// record it referred to before.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ItemNotFound] if either record doesn't
+    /// exist.
+    pub fn link(&mut self, uuid: &str, relation: &str, target: &str) -> Result<(), DatabaseError> {
+        let target = self.get(target)?.clone();
+
+        self.modify(uuid, |record| {
+            record.link(relation, &target);
+        })
+    }
+
+    /// Removes the reference of a record through a relation, returning it.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ItemNotFound] if the record doesn't exist.
+    pub fn unlink(
+        &mut self,
+        uuid: &str,
+        relation: &str,
+    ) -> Result<Option<RecordRef>, DatabaseError> {
+        self.modify(uuid, |record| record.unlink(relation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests following references both ways as they change
+    #[test]
+    fn related_records() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Relation test", None, false);
+        let player = Record::new("Player".into());
+        let inventory = Record::new("Inventory".into());
+        let mut sword = Record::new("Item".into());
+        sword.link("inventory", &inventory);
+
+        db.create(player.clone())?;
+        db.create(inventory.clone())?;
+        db.create(sword.clone())?;
+        db.link(&inventory.uuid, "owner", &player.uuid)?;
+
+        assert_eq!(db.related(&sword.uuid, "inventory")?.uuid, inventory.uuid);
+        assert_eq!(db.related(&inventory.uuid, "owner")?, &player);
+        assert!(db.related(&player.uuid, "owner").is_err());
+        assert_eq!(db.referrers(&inventory.uuid, "inventory"), vec![&sword]);
+        assert_eq!(db.referrers(&player.uuid, "owner").len(), 1);
+        assert!(db.link(&sword.uuid, "inventory", "missing").is_err());
+
+        db.link(&sword.uuid, "inventory", &player.uuid)?;
+        assert!(db.referrers(&inventory.uuid, "inventory").is_empty());
+        assert_eq!(
+            db.unlink(&sword.uuid, "inventory")?.unwrap().model,
+            "Player"
+        );
+        assert!(db.referrers(&player.uuid, "inventory").is_empty());
+
+        Ok(())
+    }
+
+    /// Tests references are checked when records are written, and when
+    /// followed
+    #[test]
+    fn reference_integrity() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Relation test", None, false);
+        let player = Record::new("Player".into());
+        let mut sword = Record::new("Item".into());
+        sword.link("owner", &player);
+
+        assert!(matches!(
+            db.create(sword.clone()),
+            Err(DatabaseError::BrokenReference(relation)) if relation == "owner"
+        ));
+        assert!(db.upsert(sword.clone()).is_err());
+
+        let mut node = Record::new("Node".into());
+        node.link("next", &node.clone());
+        db.create(node)?;
+
+        db.create(player.clone())?;
+        db.create(sword.clone())?;
+
+        let mut renamed = player.clone();
+        renamed.uuid = "renamed".into();
+        assert!(matches!(
+            db.update(&player, renamed),
+            Err(DatabaseError::RelationRestricted(_))
+        ));
+
+        let mut wrong = sword.clone();
+        wrong.links.get_mut("owner").unwrap().model = "Item".into();
+        assert!(matches!(
+            db.update(&sword, wrong),
+            Err(DatabaseError::BrokenReference(_))
+        ));
+
+        db.modify(&player.uuid, |player| player.model = "Ghost".into())?;
+        assert!(matches!(
+            db.related(&sword.uuid, "owner"),
+            Err(DatabaseError::BrokenReference(_))
+        ));
+
+        Ok(())
+    }
+
+    /// Tests each rule for destroying records that others refer to
+    #[test]
+    fn destroy_rules() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Relation test", None, false);
+        let player = Record::new("Player".into());
+        let mut inventory = Record::new("Inventory".into());
+        inventory.link("owner", &player);
+        let mut sword = Record::new("Item".into());
+        sword.link("inventory", &inventory);
+        let mut quest = Record::new("Quest".into());
+        quest.link("hero", &player);
+
+        for record in [&player, &inventory, &sword, &quest] {
+            db.create(record.clone())?;
+        }
+
+        assert!(matches!(
+            db.destroy(&player),
+            Err(DatabaseError::RelationRestricted(_))
+        ));
+        assert_eq!(db.len(), 4);
+
+        db.on_destroy("owner", OnDestroy::Cascade);
+        db.on_destroy("inventory", OnDestroy::Cascade);
+        db.on_destroy("hero", OnDestroy::Nullify);
+        db.destroy(&player)?;
+
+        assert_eq!(db.len(), 1);
+        assert_eq!(db.get(&quest.uuid)?.linked("hero"), None);
+
+        db.on_destroy("next", OnDestroy::Cascade);
+
+        let cycle = |db: &mut Database<Record>| -> Result<Record, DatabaseError> {
+            let a = Record::new("Node".into());
+            let mut b = Record::new("Node".into());
+            b.link("next", &a);
+            db.create(a.clone())?;
+            db.create(b.clone())?;
+            db.link(&a.uuid, "next", &b.uuid)?;
+            Ok(a)
+        };
+
+        let a = cycle(&mut db)?;
+        db.remove_by_key(&a.uuid)?;
+        assert_eq!(db.len(), 1);
+
+        cycle(&mut db)?;
+        assert_eq!(db.destroy_where(|record| record.model == "Node")?, 1);
+        assert_eq!(db.len(), 1);
+
+        assert_eq!(OnDestroy::parse("NULLIFY")?, OnDestroy::Nullify);
+        assert!(OnDestroy::parse("explode").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,431 @@
+//! Schemas describing the attributes each [Record] model should have, so a
+//! `Player` can't be created with `hp: "banana"`.
+//!
+//! A schema is registered for a model with [Database::define_model], or for
+//! many models at once from a JSON file with [Database::load_models]. Records
+//! of that model are then checked by [Database::create], [Database::upsert]
+//! and [Database::update], and every change made through them such as
+//! [Database::modify], failing with [DatabaseError::ValidationFailed].
+//! Missing attributes are filled in with their defaults along the way.
+//!
+//! Schemas only cover the attributes they list, any others are left alone.
+//! Records of models without a schema and records already in the database
+//! aren't checked.
+//!
+//! ```rust
+//! use gddb::{Database, DatabaseError, Field, FieldType, Record, Schema, Value};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("GAME", None, false);
+//!
+//!     db.define_model(
+//!         "Player",
+//!         Schema::new()
+//!             .field("hp", Field::new(FieldType::Int).required().range(0.0, 100.0))
+//!             .field("gold", Field::new(FieldType::Int).with_default(0)),
+//!     );
+//!
+//!     let mut player = Record::new("Player".into());
+//!     player.set("hp", "banana");
+//!     assert!(matches!(db.create(player.clone()), Err(DatabaseError::ValidationFailed { .. })));
+//!
+//!     player.set("hp", 50);
+//!     db.create(player.clone()).unwrap();
+//!     assert_eq!(db.get(&player.uuid).unwrap().get("gold"), Some(&Value::Int(0)));
+//! }
+//! ```
+//!
+//! The same schema as JSON, as read by [Database::load_models]:
+//!
+//! ```json
+//! {
+//!     "Player": {
+//!         "hp": { "type": "int", "required": true, "min": 0, "max": 100 },
+//!         "gold": { "type": "int", "default": 0 },
+//!         "class": { "type": "string", "enum": ["warrior", "mage"] }
+//!     }
+//! }
+//! ```
+
+use crate::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The type of value a [Field] holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    /// Any value.
+    #[default]
+    Any,
+
+    /// `true` or `false`.
+    Bool,
+
+    /// A whole number.
+    Int,
+
+    /// Any number, including whole numbers.
+    Float,
+
+    /// A string.
+    String,
+
+    /// An array of any values.
+    Array,
+
+    /// A map of any values.
+    Map,
+}
+
+impl FieldType {
+    /// If a value is of this type.
+    pub fn matches(&self, value: &Value) -> bool {
+        match self {
+            FieldType::Any => true,
+            FieldType::Bool => matches!(value, Value::Bool(_)),
+            FieldType::Int => matches!(value, Value::Int(_)),
+            FieldType::Float => matches!(value, Value::Int(_) | Value::Float(_)),
+            FieldType::String => matches!(value, Value::String(_)),
+            FieldType::Array => matches!(value, Value::Array(_)),
+            FieldType::Map => matches!(value, Value::Map(_)),
+        }
+    }
+}
+
+impl Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FieldType::Any => "any",
+            FieldType::Bool => "bool",
+            FieldType::Int => "int",
+            FieldType::Float => "float",
+            FieldType::String => "string",
+            FieldType::Array => "array",
+            FieldType::Map => "map",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// The rules for a single attribute in a [Schema].
+///
+/// A `null` attribute counts as missing. Ranges limit numbers by their value,
+/// and strings and arrays by their length.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Field {
+    /// The type of the attribute.
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+
+    /// If a record without the attribute, and without a default for it, is
+    /// invalid.
+    pub required: bool,
+
+    /// The value given to a record without the attribute.
+    pub default: Option<Value>,
+
+    /// The smallest the attribute may be.
+    pub min: Option<f64>,
+
+    /// The largest the attribute may be.
+    pub max: Option<f64>,
+
+    /// The only values the attribute may have.
+    #[serde(rename = "enum")]
+    pub one_of: Option<Vec<Value>>,
+}
+
+impl Field {
+    /// Creates an optional field of a type, with no other rules.
+    pub fn new(kind: FieldType) -> Self {
+        Self {
+            kind,
+            ..Self::default()
+        }
+    }
+
+    /// Makes the field required.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Gives the field a default value.
+    pub fn with_default(mut self, value: impl Into<Value>) -> Self {
+        self.default = Some(value.into());
+        self
+    }
+
+    /// Limits the field to between `min` and `max` inclusive.
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    /// Limits the field to one of a list of values.
+    pub fn one_of(mut self, values: Vec<Value>) -> Self {
+        self.one_of = Some(values);
+        self
+    }
+
+    /// Checks a value of the field, returning why it's invalid.
+    fn check(&self, value: &Value) -> Result<(), String> {
+        if !self.kind.matches(value) {
+            return Err(format!("must be of type {}", self.kind));
+        }
+
+        let size = match value {
+            Value::String(s) => Some(s.chars().count() as f64),
+            Value::Array(items) => Some(items.len() as f64),
+            value => value.as_f64(),
+        };
+
+        if let (Some(size), Some(min)) = (size, self.min) {
+            if size < min {
+                return Err(format!("must be at least {}", min));
+            }
+        }
+
+        if let (Some(size), Some(max)) = (size, self.max) {
+            if size > max {
+                return Err(format!("must be at most {}", max));
+            }
+        }
+
+        match &self.one_of {
+            Some(values) if !values.contains(value) => {
+                Err(format!("must be one of {}", Value::Array(values.clone())))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The attributes a model should have, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Schema {
+    /// The rules for each attribute, by its path.
+    pub fields: BTreeMap<String, Field>,
+}
+
+impl Schema {
+    /// Creates a schema without any fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the rules for an attribute by its `.` separated path, such as
+    /// `stats.hp`.
+    pub fn field(mut self, path: impl Into<String>, field: Field) -> Self {
+        self.fields.insert(path.into(), field);
+        self
+    }
+
+    /// Converts a schema from its [Value] form, as written in the files read
+    /// by [Database::load_models].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::BadQuery] if the value isn't a schema.
+    pub fn from_value(value: Value) -> Result<Self, DatabaseError> {
+        value
+            .deserialize_into()
+            .map_err(|e| DatabaseError::BadQuery(format!("invalid schema: {}", e)))
+    }
+
+    /// Checks the attributes of a record, filling in the defaults of any that
+    /// are missing.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::ValidationFailed] for the first field that
+    /// is invalid.
+    pub fn validate(&self, attributes: &mut Value) -> Result<(), DatabaseError> {
+        for (path, field) in self.fields.iter() {
+            let missing = attributes.get_path(path).map_or(true, Value::is_null);
+
+            if missing {
+                match &field.default {
+                    Some(default) => {
+                        attributes.set_path(path, default.clone())?;
+                    }
+                    None if field.required => {
+                        return Err(DatabaseError::ValidationFailed {
+                            field: path.clone(),
+                            reason: "is required".into(),
+                        })
+                    }
+                    None => continue,
+                }
+            }
+
+            if let Some(value) = attributes.get_path(path) {
+                field
+                    .check(value)
+                    .map_err(|reason| DatabaseError::ValidationFailed {
+                        field: path.clone(),
+                        reason,
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
+    /// Registers the schema of a model, replacing any it already had. See
+    /// [crate::schema].
+    pub fn define_model(&mut self, model: impl Into<String>, schema: Schema) {
+        self.schemas.insert(model.into(), schema);
+    }
+
+    /// Removes the schema of a model, returning it.
+    pub fn drop_model(&mut self, model: &str) -> Option<Schema> {
+        self.schemas.remove(model)
+    }
+
+    /// Returns the schema of a model, if it has one.
+    pub fn schema(&self, model: &str) -> Option<&Schema> {
+        self.schemas.get(model)
+    }
+
+    /// Registers the schemas of every model in a JSON file, mapping the name
+    /// of each model to its schema. See [crate::schema] for an example.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::DatabaseNotFound] if the file doesn't
+    /// exist, or [DatabaseError::BadQuery] if it isn't valid. No schemas are
+    /// registered if it fails.
+    pub fn load_models(&mut self, path: impl AsRef<Path>) -> Result<(), DatabaseError> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Err(DatabaseError::DatabaseNotFound);
+        }
+
+        let models: BTreeMap<String, Schema> =
+            serde_json::from_str(&std::fs::read_to_string(path)?)
+                .map_err(|e| DatabaseError::BadQuery(format!("invalid schema: {}", e)))?;
+
+        self.schemas.extend(models);
+        Ok(())
+    }
+
+    /// Checks an item against the registered schemas with
+    /// [Keyed::validate], returning it with any defaults filled in.
+    pub(crate) fn validated(&self, mut item: Record) -> Result<Record, DatabaseError> {
+        item.validate(&self.schemas)?;
+        Ok(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests each rule of a field, and that creates, updates and changes made
+    /// in place are all checked
+    #[test]
+    fn validate_records() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Schema test", None, false);
+        db.define_model(
+            "Player",
+            Schema::new()
+                .field(
+                    "name",
+                    Field::new(FieldType::String).required().range(1.0, 12.0),
+                )
+                .field(
+                    "hp",
+                    Field::new(FieldType::Int)
+                        .with_default(100)
+                        .range(0.0, 100.0),
+                )
+                .field("speed", Field::new(FieldType::Float))
+                .field(
+                    "class",
+                    Field::new(FieldType::String).one_of(vec!["warrior".into(), "mage".into()]),
+                ),
+        );
+
+        let invalid = |db: &mut Database<Record>, key: &str, value: Value| {
+            let mut player = Record::new("Player".into());
+            player.set("name", "Hero");
+            player.set(key, value);
+
+            match db.create(player) {
+                Err(DatabaseError::ValidationFailed { field, .. }) => field == key,
+                _ => false,
+            }
+        };
+
+        assert!(invalid(&mut db, "name", Value::Null));
+        assert!(invalid(&mut db, "name", "".into()));
+        assert!(invalid(&mut db, "hp", "banana".into()));
+        assert!(invalid(&mut db, "hp", 101.into()));
+        assert!(invalid(&mut db, "speed", true.into()));
+        assert!(invalid(&mut db, "class", "rogue".into()));
+        assert_eq!(db.len(), 0);
+
+        let mut player = Record::new("Player".into());
+        player.set("name", "Hero");
+        player.set("speed", 2);
+        player.set("class", "mage");
+        db.create(player.clone())?;
+        assert_eq!(db.get(&player.uuid)?.get("hp"), Some(&Value::Int(100)));
+
+        assert!(db.modify(&player.uuid, |p| p.set("hp", -1)).is_err());
+        assert!(db.increment(&player.uuid, "hp", 0.5).is_err());
+        assert_eq!(db.decrement(&player.uuid, "hp", 10)?, Value::Int(90));
+
+        db.create(Record::new("Enemy".into()))?;
+        assert!(db.drop_model("Player").is_some());
+        db.modify(&player.uuid, |p| p.set("hp", "banana"))?;
+
+        Ok(())
+    }
+
+    /// Tests schemas are read from JSON files
+    #[test]
+    fn load_models() -> Result<(), DatabaseError> {
+        let json = r#"{
+            "Player": {
+                "hp": { "type": "int", "required": true, "min": 0 },
+                "class": { "type": "string", "enum": ["warrior", "mage"], "default": "warrior" }
+            }
+        }"#;
+        std::fs::write("models.json", json)?;
+
+        let mut db: Database<Record> = Database::new("Schema test", None, false);
+        db.load_models("models.json")?;
+        std::fs::remove_file("models.json")?;
+
+        let schema = db.schema("Player").unwrap();
+        assert!(schema.fields["hp"].required);
+        assert_eq!(schema.fields["class"].default, Some(Value::from("warrior")));
+
+        let mut player = Record::new("Player".into());
+        assert!(db.create(player.clone()).is_err());
+        player.set("hp", 10);
+        db.create(player.clone())?;
+        assert_eq!(
+            db.get(&player.uuid)?.get("class"),
+            Some(&Value::from("warrior"))
+        );
+
+        assert!(Schema::from_value(Value::from(vec!["hp"])).is_err());
+        assert!(matches!(
+            db.load_models("missing.json"),
+            Err(DatabaseError::DatabaseNotFound)
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+//! Full-text search, finding items by keywords such as `fire sword` across
+//! their text.
+//!
+//! Search is optional and enabled with [Database::enable_search], which builds
+//! an inverted index from each word to the items containing it and keeps it in
+//! sync as items change. Text is split into words on anything that isn't a
+//! letter or digit and compared case-insensitively.
+//!
+//! Every word of a search must match a word of an item, either exactly or as
+//! its prefix, so `fir` finds `fire` and `fireball`. Results are ranked by how
+//! often the words appear, with exact matches and rarer words counting for
+//! more. Items that are equally relevant are in no particular order.
+//!
+//! ```rust
+//! use gddb::{Database, Record};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("ITEMS", None, false);
+//!     db.enable_search();
+//!
+//!     let mut sword = Record::new("Item".into());
+//!     sword.set("name", "Flaming Sword");
+//!     sword.set("description", "A sword wreathed in fire.");
+//!     db.create(sword.clone()).unwrap();
+//!
+//!     let mut staff = Record::new("Item".into());
+//!     staff.set("name", "Fire Staff");
+//!     db.create(staff).unwrap();
+//!
+//!     assert_eq!(db.search("fire sword").unwrap(), vec![&sword]);
+//! }
+//! ```
+
+use crate::prelude::*;
+use std::collections::BTreeMap;
+
+/// Extracts the text an item is searched by, see
+/// [Database::enable_search_with].
+pub type SearchFn<T> = fn(&T) -> String;
+
+/// An inverted index from words to the items containing them.
+#[derive(Debug, Clone)]
+pub(crate) struct SearchIndex<T: Keyed> {
+    extract: SearchFn<T>,
+
+    /// How many times each word appears in each item.
+    words: BTreeMap<String, HashMap<T::Key, usize>>,
+
+    /// The distinct words of each item, to remove it again.
+    items: HashMap<T::Key, Vec<String>>,
+}
+
+impl<T: Keyed> SearchIndex<T> {
+    fn new(extract: SearchFn<T>) -> Self {
+        Self {
+            extract,
+            words: BTreeMap::new(),
+            items: HashMap::new(),
+        }
+    }
+
+    /// Adds an item to the index.
+    pub(crate) fn insert(&mut self, item: &T) {
+        let key = item.primary_key();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+
+        for word in tokenize(&(self.extract)(item)) {
+            *counts.entry(word).or_default() += 1;
+        }
+
+        for (word, count) in counts.iter() {
+            self.words
+                .entry(word.clone())
+                .or_default()
+                .insert(key.clone(), *count);
+        }
+
+        self.items.insert(key, counts.into_keys().collect());
+    }
+
+    /// Removes an item from the index, dropping words no other item has.
+    pub(crate) fn remove(&mut self, item: &T) {
+        let key = item.primary_key();
+
+        for word in self.items.remove(&key).into_iter().flatten() {
+            if let Some(items) = self.words.get_mut(&word) {
+                items.remove(&key);
+
+                if items.is_empty() {
+                    self.words.remove(&word);
+                }
+            }
+        }
+    }
+
+    /// Returns the keys of every item matching all words of `text` along with
+    /// their relevance, most relevant first.
+    fn search(&self, text: &str) -> Vec<(&T::Key, f64)> {
+        let terms = tokenize(text);
+        let total = self.items.len() as f64;
+        let mut scores: HashMap<&T::Key, (usize, f64)> = HashMap::new();
+
+        for (i, term) in terms.iter().enumerate() {
+            let matches = self
+                .words
+                .range(term.clone()..)
+                .take_while(|(word, _)| word.starts_with(term.as_str()));
+
+            for (word, items) in matches {
+                let weight = if word == term { 1.0 } else { 0.5 };
+                let rarity = (1.0 + total / items.len() as f64).ln();
+
+                for (key, count) in items {
+                    let (matched, score) = scores.entry(key).or_default();
+
+                    if *matched == i {
+                        *matched += 1;
+                    }
+
+                    *score += weight * rarity * *count as f64;
+                }
+            }
+        }
+
+        let mut results: Vec<(&T::Key, f64)> = scores
+            .into_iter()
+            .filter(|(_, (matched, _))| *matched == terms.len())
+            .map(|(key, (_, score))| (key, score))
+            .collect();
+
+        results.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        results
+    }
+}
+
+impl<T: Keyed> PartialEq for SearchIndex<T> {
+    /// Indexes are equal when they hold the same words, extractors can't be
+    /// meaningfully compared.
+    fn eq(&self, other: &Self) -> bool {
+        self.words == other.words
+    }
+}
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
+    /// Enables full-text search over the text returned by `extract`, see
+    /// [crate::search]. Any existing search index is replaced.
+    ///
+    /// Like other indexes, search must be enabled again after loading.
+    pub fn enable_search_with(&mut self, extract: SearchFn<Record>) {
+        let mut index = SearchIndex::new(extract);
+
+        for item in self.items.values() {
+            index.insert(item);
+        }
+
+        self.search = Some(index);
+    }
+
+    /// Disables full-text search, dropping its index.
+    pub fn disable_search(&mut self) {
+        self.search = None;
+    }
+
+    /// If full-text search has been enabled.
+    pub fn search_enabled(&self) -> bool {
+        self.search.is_some()
+    }
+
+    /// Finds every item matching all words of `text`, most relevant first,
+    /// see [crate::search].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::IndexNotFound] if search hasn't been
+    /// enabled.
+    pub fn search(&self, text: &str) -> Result<Vec<&Record>, DatabaseError> {
+        let index = self.search.as_ref().ok_or(DatabaseError::IndexNotFound)?;
+
+        Ok(index
+            .search(text)
+            .into_iter()
+            .filter_map(|(key, _)| self.items.get(key))
+            .collect())
+    }
+}
+
+impl Database<Record> {
+    /// Enables full-text search over every string inside of the attributes of
+    /// each record, see [crate::search].
+    pub fn enable_search(&mut self) {
+        self.enable_search_with(record_text);
+    }
+}
+
+/// Joins every string inside of a record's attributes.
+fn record_text(record: &Record) -> String {
+    fn collect(value: &Value, text: &mut String) {
+        match value {
+            Value::String(s) => {
+                text.push_str(s);
+                text.push('\n');
+            }
+            Value::Array(items) => items.iter().for_each(|item| collect(item, text)),
+            Value::Map(map) => map.values().for_each(|item| collect(item, text)),
+            _ => {}
+        }
+    }
+
+    let mut text = String::new();
+    collect(&record.attributes, &mut text);
+    text
+}
+
+/// Splits text into lowercase words.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests matching every word by prefix, ranking and keeping the index in
+    /// sync with changes
+    #[test]
+    fn search_records() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Search test", None, false);
+        assert!(matches!(
+            db.search("sword"),
+            Err(DatabaseError::IndexNotFound)
+        ));
+
+        let mut sword = Record::new("Item".into());
+        sword.set("name", "Sword of FIRE");
+        sword.set("tags", vec!["fire", "blade", "sword"]);
+        db.create(sword.clone())?;
+
+        let mut staff = Record::new("Item".into());
+        staff.set("name", "Fireball Staff");
+        db.create(staff.clone())?;
+
+        db.enable_search();
+
+        let mut shield = Record::new("Item".into());
+        shield.set("name", "Shield");
+        shield.set(
+            "lore",
+            Value::from(vec![Value::from("Blessed by the sword saint")]),
+        );
+        db.create(shield.clone())?;
+
+        assert_eq!(db.search("fire")?, vec![&sword, &staff]);
+        assert_eq!(db.search("SWORD")?, vec![&sword, &shield]);
+        assert_eq!(db.search("fire sword")?, vec![&sword]);
+        assert_eq!(db.search("bla, sai")?, Vec::<&Record>::new());
+        assert!(db.search("axe")?.is_empty());
+
+        db.destroy(&sword)?;
+        assert_eq!(db.search("fire")?, vec![&staff]);
+
+        let mut renamed = staff.clone();
+        renamed.set("name", "Ice Staff");
+        db.update(&staff, renamed.clone())?;
+        assert!(db.search("fire")?.is_empty());
+        assert_eq!(db.search("ice")?, vec![&renamed]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+//! A query builder for [Record] databases, adding sorting, limits and
+//! pagination on top of [Query] filters.
+//!
+//! Results are always in a stable order: records are sorted by each
+//! [Select::order_by] path in turn and then by uuid, so the same select over
+//! the same records always returns them in the same order.
+//!
+//! Large result sets can be paged through with [Select::offset], or with the
+//! [Cursor] returned by [Select::page], which keeps its place even if records
+//! before it are created or destroyed between pages.
+//!
+//! ```rust
+//! use gddb::{Database, Query, Record, SortOrder, Value};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("GAME", None, false);
+//!
+//!     for score in [30, 10, 20] {
+//!         let mut entry = Record::new("Score".into());
+//!         entry.set("score", score);
+//!         db.create(entry).unwrap();
+//!     }
+//!
+//!     let top = db
+//!         .select()
+//!         .filter(Query::parse(r#"model == "Score""#).unwrap())
+//!         .order_by("score", SortOrder::Desc)
+//!         .limit(2)
+//!         .page();
+//!
+//!     let scores: Vec<_> = top.records.iter().map(|r| r.get("score").unwrap()).collect();
+//!     assert_eq!(scores, vec![&Value::Int(30), &Value::Int(20)]);
+//!
+//!     let rest = db.select().order_by("score", SortOrder::Desc).after(top.next.unwrap());
+//!     assert_eq!(rest.records().len(), 1);
+//! }
+//! ```
+
+use crate::prelude::*;
+use crate::query::resolve;
+use std::cmp::Ordering;
+
+/// The direction to sort by in [Select::order_by].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// Smallest first.
+    #[default]
+    Asc,
+
+    /// Largest first.
+    Desc,
+}
+
+/// A position in the results of a [Select], returned by [Select::page] to
+/// fetch the following page with [Select::after].
+///
+/// Cursors can be passed around as strings with [Cursor::encode] and
+/// [Cursor::decode].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    values: Vec<Value>,
+    uuid: String,
+}
+
+impl Cursor {
+    /// Encodes the cursor as a string.
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Decodes a cursor made by [Cursor::encode].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::BadQuery] if the string isn't a cursor.
+    pub fn decode(cursor: &str) -> Result<Self, DatabaseError> {
+        serde_json::from_str(cursor)
+            .map_err(|_| DatabaseError::BadQuery(format!("invalid cursor {:?}", cursor)))
+    }
+}
+
+/// A page of results from [Select::page].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<'a> {
+    /// The records on this page, in order.
+    pub records: Vec<&'a Record>,
+
+    /// Where the next page starts, or [Option::None] if this is the last page.
+    pub next: Option<Cursor>,
+}
+
+/// A query being built with [Database::select], see the
+/// [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Select<'a> {
+    db: &'a Database<Record>,
+    filter: Option<Query>,
+    order: Vec<(String, SortOrder)>,
+    after: Option<Cursor>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl<'a> Select<'a> {
+    /// Only selects records matched by a query, on top of any previous
+    /// filters.
+    pub fn filter(mut self, query: Query) -> Self {
+        self.filter = Some(match self.filter.take() {
+            Some(filter) => Query::And(Box::new(filter), Box::new(query)),
+            None => query,
+        });
+        self
+    }
+
+    /// Sorts by the value at a path, which is resolved the same way as paths
+    /// in a [Query]. Calling this again sorts records that are equal so far
+    /// by another path.
+    ///
+    /// Values are sorted with [Value::total_cmp], with missing values sorted
+    /// as `null`.
+    pub fn order_by(mut self, path: impl Into<String>, order: SortOrder) -> Self {
+        self.order.push((path.into(), order));
+        self
+    }
+
+    /// Only selects records after a [Cursor] from [Select::page].
+    pub fn after(mut self, cursor: Cursor) -> Self {
+        self.after = Some(cursor);
+        self
+    }
+
+    /// Skips a number of records.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Selects at most a number of records.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns the selected records, in order.
+    pub fn records(&self) -> Vec<&'a Record> {
+        self.page().records
+    }
+
+    /// Returns the selected records along with a [Cursor] for the next page,
+    /// if a [Select::limit] was set and there are more records.
+    pub fn page(&self) -> Page<'a> {
+        let candidates = match &self.filter {
+            Some(query) => self.db.filter(query),
+            None => self.db.items.values().collect(),
+        };
+
+        let mut sorted: Vec<(Vec<Value>, &Record)> = candidates
+            .into_iter()
+            .map(|record| (self.sort_values(record), record))
+            .collect();
+        sorted.sort_by(|(a, a_record), (b, b_record)| {
+            self.compare(a, &a_record.uuid, b, &b_record.uuid)
+        });
+
+        let start = match &self.after {
+            Some(cursor) => sorted.partition_point(|(values, record)| {
+                self.compare(values, &record.uuid, &cursor.values, &cursor.uuid)
+                    != Ordering::Greater
+            }),
+            None => 0,
+        };
+
+        let mut remaining = sorted.into_iter().skip(start + self.offset);
+        let page: Vec<(Vec<Value>, &Record)> = match self.limit {
+            Some(limit) => remaining.by_ref().take(limit).collect(),
+            None => remaining.by_ref().collect(),
+        };
+
+        let next = match (remaining.next(), page.last()) {
+            (Some(_), Some((values, record))) => Some(Cursor {
+                values: values.clone(),
+                uuid: record.uuid.clone(),
+            }),
+            _ => None,
+        };
+
+        Page {
+            records: page.into_iter().map(|(_, record)| record).collect(),
+            next,
+        }
+    }
+
+    /// The values a record is sorted by.
+    fn sort_values(&self, record: &Record) -> Vec<Value> {
+        self.order
+            .iter()
+            .map(|(path, _)| resolve(record, path).map_or(Value::Null, |value| value.into_owned()))
+            .collect()
+    }
+
+    /// Orders two records by their sort values, and then their uuids.
+    fn compare(&self, a: &[Value], a_uuid: &str, b: &[Value], b_uuid: &str) -> Ordering {
+        self.order
+            .iter()
+            .zip(a.iter().zip(b))
+            .map(|((_, order), (a, b))| match order {
+                SortOrder::Asc => a.total_cmp(b),
+                SortOrder::Desc => b.total_cmp(a),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a_uuid.cmp(b_uuid))
+    }
+}
+
+impl Database<Record> {
+    /// Starts building a query with sorting and pagination, see
+    /// [crate::select].
+    pub fn select(&self) -> Select<'_> {
+        Select {
+            db: self,
+            filter: None,
+            order: Vec::new(),
+            after: None,
+            offset: 0,
+            limit: None,
+        }
+    }
+}
+
+/// Parses a list of paths to sort by, such as `score desc, name`, where each
+/// path may be followed by `asc` or `desc`.
+///
+/// # Errors
+///
+/// Will return [DatabaseError::BadQuery] if a path is followed by anything
+/// else.
+pub fn parse_order(order: &str) -> Result<Vec<(String, SortOrder)>, DatabaseError> {
+    order
+        .split(',')
+        .map(str::split_whitespace)
+        .filter_map(|mut words| Some((words.next()?, words.next(), words.next())))
+        .map(|(path, order, rest)| {
+            let order = match (order, rest) {
+                (None, None) => SortOrder::Asc,
+                (Some(order), None) if order.eq_ignore_ascii_case("asc") => SortOrder::Asc,
+                (Some(order), None) if order.eq_ignore_ascii_case("desc") => SortOrder::Desc,
+                _ => {
+                    return Err(DatabaseError::BadQuery(format!(
+                        "invalid ordering for {:?}",
+                        path
+                    )))
+                }
+            };
+
+            Ok((path.to_string(), order))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests sorting by several paths with limits and offsets
+    #[test]
+    fn sorted_results() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Select test", None, false);
+
+        for (name, score) in [("b", 20), ("a", 20), ("c", 30), ("d", 10)] {
+            let mut entry = Record::new("Score".into());
+            entry.set("name", name);
+            entry.set("score", score);
+            db.create(entry)?;
+        }
+        db.create(Record::new("Player".into()))?;
+
+        let names = |records: Vec<&Record>| -> Vec<String> {
+            records
+                .iter()
+                .map(|r| r.get("name").and_then(Value::as_str).unwrap().into())
+                .collect()
+        };
+
+        let select = db
+            .select()
+            .filter(Query::parse(r#"model == "Score""#)?)
+            .order_by("score", SortOrder::Desc)
+            .order_by("attributes.name", SortOrder::Asc);
+
+        assert_eq!(names(select.records()), vec!["c", "a", "b", "d"]);
+        assert_eq!(
+            names(select.clone().offset(1).limit(2).records()),
+            vec!["a", "b"]
+        );
+        assert!(select.clone().offset(10).records().is_empty());
+
+        let all = db.select().order_by("score", SortOrder::Asc).records();
+        assert_eq!(all[0].model, "Player");
+
+        Ok(())
+    }
+
+    /// Tests paging through results with cursors, even when records are
+    /// destroyed between pages
+    #[test]
+    fn cursor_pages() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Select test", None, false);
+
+        for score in 0..5 {
+            let mut entry = Record::new("Score".into());
+            entry.set("score", score);
+            db.create(entry)?;
+        }
+
+        let first = db
+            .select()
+            .order_by("score", SortOrder::Asc)
+            .limit(2)
+            .page();
+        let cursor = Cursor::decode(&first.next.unwrap().encode())?;
+        let destroyed = first.records[0].clone();
+
+        db.destroy(&destroyed)?;
+
+        let second = db
+            .select()
+            .order_by("score", SortOrder::Asc)
+            .limit(2)
+            .after(cursor)
+            .page();
+        let scores: Vec<&Value> = second
+            .records
+            .iter()
+            .map(|r| r.get("score").unwrap())
+            .collect();
+        assert_eq!(scores, vec![&Value::Int(2), &Value::Int(3)]);
+
+        let last = db
+            .select()
+            .order_by("score", SortOrder::Asc)
+            .limit(2)
+            .after(second.next.unwrap())
+            .page();
+        assert_eq!(last.records.len(), 1);
+        assert_eq!(last.next, None);
+
+        assert_eq!(
+            parse_order("score desc, name")?,
+            vec![
+                ("score".to_string(), SortOrder::Desc),
+                ("name".to_string(), SortOrder::Asc)
+            ]
+        );
+        assert!(parse_order("score sideways").is_err());
+
+        Ok(())
+    }
+}
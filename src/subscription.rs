@@ -0,0 +1,288 @@
+//! Live queries, keeping the results of a [Query] up to date as records change
+//! so quest logs and HUDs can follow them without searching again.
+//!
+//! ```rust
+//! use gddb::{Database, Delta, Record};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("GAME", None, false);
+//!     let quests = db
+//!         .subscribe_where(r#"model == "Quest" and state == "active""#)
+//!         .unwrap();
+//!
+//!     let mut quest = Record::new("Quest".into());
+//!     quest.set("state", "active");
+//!     db.create(quest.clone()).unwrap();
+//!
+//!     assert_eq!(quests.results(), vec![quest.clone()]);
+//!     assert_eq!(quests.take_deltas(), vec![Delta::Added(quest)]);
+//! }
+//! ```
+
+use crate::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// A change to the results of a [Subscription].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delta {
+    /// A record started matching the query.
+    Added(Record),
+
+    /// A record stopped matching the query, or was destroyed.
+    Removed(Record),
+
+    /// A record that still matches the query was changed, with its value
+    /// before and after.
+    Modified { old: Record, new: Record },
+}
+
+/// The results of a [Subscription], shared with its observer.
+#[derive(Debug, Default)]
+struct Results {
+    records: BTreeMap<String, Record>,
+    deltas: Vec<Delta>,
+}
+
+impl Results {
+    /// Updates the results for a change to a record, returning the delta
+    /// if the results changed.
+    fn apply(&mut self, query: &Query, change: &Change<Record>) -> Option<Delta> {
+        let record = change.item();
+        let matched = self.records.contains_key(&record.uuid);
+        let matches = !matches!(change, Change::Destroyed(_)) && query.matches(record);
+
+        let delta = match (matched, matches) {
+            (false, true) => Delta::Added(record.clone()),
+            (true, false) => Delta::Removed(self.records.remove(&record.uuid)?),
+            (true, true) => Delta::Modified {
+                old: self.records.get(&record.uuid)?.clone(),
+                new: record.clone(),
+            },
+            (false, false) => return None,
+        };
+
+        if matches {
+            self.records.insert(record.uuid.clone(), record.clone());
+        }
+
+        Some(delta)
+    }
+}
+
+/// A live query registered with [Database::subscribe], see the
+/// [module docs](self).
+///
+/// Clones share the same results.
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    query: Query,
+    observer: ObserverId,
+    results: Arc<Mutex<Results>>,
+}
+
+impl Subscription {
+    /// The query this subscription follows.
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// Every record currently matching the query, ordered by uuid.
+    pub fn results(&self) -> Vec<Record> {
+        self.lock().records.values().cloned().collect()
+    }
+
+    /// Takes every [Delta] since the subscription was made or this was last
+    /// called, oldest first.
+    pub fn take_deltas(&self) -> Vec<Delta> {
+        std::mem::take(&mut self.lock().deltas)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Results> {
+        self.results.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Database<Record> {
+    /// Registers a live query, whose results are kept up to date as records
+    /// change until it is removed with [Database::unsubscribe].
+    pub fn subscribe(&mut self, query: Query) -> Subscription {
+        let results = Results {
+            records: self.matching(&query),
+            deltas: Vec::new(),
+        };
+        let results = Arc::new(Mutex::new(results));
+        let observer = self.follow(&query, &results);
+
+        Subscription {
+            query,
+            observer,
+            results,
+        }
+    }
+
+    /// Registers a live query from a query string, see
+    /// [Database::subscribe].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::BadQuery] if the query is not valid.
+    pub fn subscribe_where(&mut self, query: &str) -> Result<Subscription, DatabaseError> {
+        Ok(self.subscribe(Query::parse(query)?))
+    }
+
+    /// Moves a subscription made on another database, such as one replaced by
+    /// loading a save, over to this one. The results are replaced with the
+    /// matching records of this database, adding a [Delta] for every
+    /// difference.
+    ///
+    /// Clones of `subscription` share the moved results, while the returned
+    /// subscription is the one to pass to [Database::unsubscribe].
+    pub fn resubscribe(&mut self, subscription: &Subscription) -> Subscription {
+        let records = self.matching(&subscription.query);
+        let mut results = subscription.lock();
+        let mut before = std::mem::replace(&mut results.records, records);
+        let mut deltas = Vec::new();
+
+        for (uuid, new) in results.records.iter() {
+            match before.remove(uuid) {
+                Some(old) if &old == new => {}
+                Some(old) => deltas.push(Delta::Modified {
+                    old,
+                    new: new.clone(),
+                }),
+                None => deltas.push(Delta::Added(new.clone())),
+            }
+        }
+
+        deltas.extend(before.into_values().map(Delta::Removed));
+        results.deltas.extend(deltas);
+        drop(results);
+
+        Subscription {
+            query: subscription.query.clone(),
+            observer: self.follow(&subscription.query, &subscription.results),
+            results: subscription.results.clone(),
+        }
+    }
+
+    /// Stops updating a live query, returning if it was registered.
+    pub fn unsubscribe(&mut self, subscription: &Subscription) -> bool {
+        self.unobserve(subscription.observer)
+    }
+
+    /// Every record matching a query, by uuid.
+    fn matching(&self, query: &Query) -> BTreeMap<String, Record> {
+        self.filter(query)
+            .into_iter()
+            .map(|record| (record.uuid.clone(), record.clone()))
+            .collect()
+    }
+
+    /// Registers an observer keeping results up to date with a query.
+    fn follow(&mut self, query: &Query, results: &Arc<Mutex<Results>>) -> ObserverId {
+        let observed = (query.clone(), results.clone());
+
+        self.observe(move |change| {
+            let (query, results) = &observed;
+            let mut results = results.lock().unwrap_or_else(|e| e.into_inner());
+
+            if let Some(delta) = results.apply(query, change) {
+                results.deltas.push(delta);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests records entering, changing inside of and leaving the results
+    #[test]
+    fn live_results() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Subscriptions test", None, false);
+
+        let mut done = Record::new("Quest".into());
+        done.set("state", "done");
+        db.create(done.clone())?;
+
+        let quests = db.subscribe_where(r#"model == "Quest" and state == "active""#)?;
+        assert!(quests.results().is_empty());
+
+        let mut active = done.clone();
+        active.set("state", "active");
+        db.update(&done, active.clone())?;
+
+        let mut progressed = active.clone();
+        progressed.set("progress", 1);
+        db.update(&active, progressed.clone())?;
+
+        db.create(Record::new("Enemy".into()))?;
+        assert_eq!(quests.results(), vec![progressed.clone()]);
+
+        db.destroy(&progressed)?;
+        assert!(quests.results().is_empty());
+
+        assert_eq!(
+            quests.take_deltas(),
+            vec![
+                Delta::Added(active.clone()),
+                Delta::Modified {
+                    old: active,
+                    new: progressed.clone(),
+                },
+                Delta::Removed(progressed),
+            ]
+        );
+        assert!(quests.take_deltas().is_empty());
+
+        assert!(db.unsubscribe(&quests));
+        db.create(done)?;
+        assert!(quests.take_deltas().is_empty());
+
+        Ok(())
+    }
+
+    /// Tests moving a subscription to a loaded database reports what changed
+    #[test]
+    fn resubscribe_loaded() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Subscriptions test", None, false);
+        let kept = Record::new("Quest".into());
+        let mut changed = Record::new("Quest".into());
+        let lost = Record::new("Quest".into());
+        db.create(kept.clone())?;
+        db.create(changed.clone())?;
+        db.create(lost.clone())?;
+
+        let quests = db.subscribe_where(r#"model == "Quest""#)?;
+        let mut loaded = db.clone();
+        let gained = Record::new("Quest".into());
+        let old = changed.clone();
+        changed.set("state", "done");
+        loaded.update(&old, changed.clone())?;
+        loaded.destroy(&lost)?;
+        loaded.create(gained.clone())?;
+
+        let moved = loaded.resubscribe(&quests);
+        let mut deltas = quests.take_deltas();
+        deltas.sort_by_key(|delta| format!("{:?}", delta));
+
+        let mut expected = vec![
+            Delta::Added(gained.clone()),
+            Delta::Modified {
+                old,
+                new: changed.clone(),
+            },
+            Delta::Removed(lost),
+        ];
+        expected.sort_by_key(|delta| format!("{:?}", delta));
+        assert_eq!(deltas, expected);
+
+        loaded.destroy(&gained)?;
+        assert_eq!(quests.results().len(), 2);
+        assert!(loaded.unsubscribe(&moved));
+
+        Ok(())
+    }
+}
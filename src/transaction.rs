@@ -0,0 +1,278 @@
+//! Transactions, grouping several changes to a [Database] so they either all
+//! happen or none do.
+//!
+//! Changes made inside of a transaction are applied straight away so they can
+//! be read back, while the state they replaced is remembered so
+//! [Database::rollback] can restore it. When [Database::journal] is enabled,
+//! the changes are only written to the journal once the transaction is
+//! committed, as a single entry.
+//!
+//! Transactions can be nested, in which case only committing the outermost
+//! transaction makes the changes final.
+//!
+//! ```rust
+//! use gddb::{Database, DatabaseError, Record};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("GAME", None, false);
+//!     let sword = Record::new("Item".into());
+//!
+//!     let result: Result<(), DatabaseError> = db.transaction(|db| {
+//!         db.create(sword.clone())?;
+//!         db.create(sword.clone())?;
+//!
+//!         Err(DatabaseError::ItemNotFound)
+//!     });
+//!
+//!     assert!(result.is_err());
+//!     assert_eq!(db.len(), 0);
+//! }
+//! ```
+
+use crate::history::Edit;
+use crate::journal::Entry;
+use crate::prelude::*;
+
+/// The state of the transactions open on a [Database].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Transaction<T: Keyed> {
+    /// Every change made, oldest first.
+    edits: Vec<Edit<T::Key, T>>,
+
+    /// Journal entries waiting for the outermost transaction to commit.
+    pub(crate) journal: Vec<Entry>,
+
+    /// The lengths of `edits` and `journal` when each nested transaction
+    /// began, innermost last.
+    savepoints: Vec<(usize, usize)>,
+}
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
+    /// Runs `f` inside of a transaction, committing its changes if it returns
+    /// [Result::Ok] and rolling them back if it returns an error.
+    pub fn transaction<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, DatabaseError>,
+    ) -> Result<R, DatabaseError> {
+        self.begin();
+
+        match f(self) {
+            Ok(value) => {
+                self.commit()?;
+                Ok(value)
+            }
+            Err(error) => {
+                self.rollback()?;
+                Err(error)
+            }
+        }
+    }
+
+    /// Begins a transaction, which must be finished with [Database::commit]
+    /// or [Database::rollback].
+    pub fn begin(&mut self) {
+        let transaction = self.transaction.get_or_insert_with(|| Transaction {
+            edits: Vec::new(),
+            journal: Vec::new(),
+            savepoints: Vec::new(),
+        });
+
+        transaction
+            .savepoints
+            .push((transaction.edits.len(), transaction.journal.len()));
+    }
+
+    /// Commits the innermost transaction. Committing the outermost transaction
+    /// writes its changes to the journal, rolling them back if that fails, and
+    /// records them as a single step in the history.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::NoTransaction] if no transaction was begun.
+    pub fn commit(&mut self) -> Result<(), DatabaseError> {
+        let transaction = self
+            .transaction
+            .as_mut()
+            .ok_or(DatabaseError::NoTransaction)?;
+
+        transaction.savepoints.pop();
+
+        if !transaction.savepoints.is_empty() {
+            return Ok(());
+        }
+
+        let transaction = self.transaction.take().unwrap();
+
+        if let Err(error) = self.write_journal(transaction.journal) {
+            self.restore_edits(transaction.edits);
+            return Err(error);
+        }
+
+        self.finish_edits(transaction.edits);
+        Ok(())
+    }
+
+    /// Rolls back every change made since the innermost transaction began.
+    ///
+    /// Only items are restored, indexes and unique constraints created or
+    /// dropped during the transaction are kept as they are. Constraints
+    /// aren't checked against the restored items, which met them before the
+    /// transaction unless a constraint was created during it.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::NoTransaction] if no transaction was begun.
+    pub fn rollback(&mut self) -> Result<(), DatabaseError> {
+        let transaction = self
+            .transaction
+            .as_mut()
+            .ok_or(DatabaseError::NoTransaction)?;
+
+        let (edits_len, journal_len) = transaction.savepoints.pop().unwrap_or_default();
+        let edits = transaction.edits.split_off(edits_len);
+        transaction.journal.truncate(journal_len);
+
+        if transaction.savepoints.is_empty() {
+            self.transaction = None;
+        }
+
+        self.restore_edits(edits);
+        Ok(())
+    }
+
+    /// If a transaction has begun and not yet been committed or rolled back.
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Remembers a change for the current transaction, or finishes it
+    /// straight away if no transaction has begun.
+    pub(crate) fn record_edit(&mut self, edit: Edit<Record::Key, Record>) {
+        match self.transaction.as_mut() {
+            Some(transaction) => transaction.edits.push(edit),
+            None => self.finish_edits(vec![edit]),
+        }
+    }
+
+    /// Notifies observers of changes that are final and records them as a
+    /// single step in the history.
+    fn finish_edits(&mut self, edits: Vec<Edit<Record::Key, Record>>) {
+        self.notify(&edits);
+        self.record_history(edits);
+    }
+
+    /// Restores the items replaced by a list of changes, newest first.
+    fn restore_edits(&mut self, edits: Vec<Edit<Record::Key, Record>>) {
+        for Edit { key, before, .. } in edits.into_iter().rev() {
+            match before {
+                Some(item) => {
+                    self.put_item(item);
+                }
+                None => {
+                    self.take_item(&key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests committed changes are kept and rolled back changes are undone,
+    /// including inside of nested transactions
+    #[test]
+    fn commit_and_rollback() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Transactions test", None, false);
+        let kept = Record::new("Testing".into());
+        let dropped = Record::new("Testing".into());
+        let mut updated = kept.clone();
+        updated.set("hp", 10);
+
+        db.begin();
+        db.create(kept.clone())?;
+        db.begin();
+        db.create(dropped.clone())?;
+        db.update(&kept, updated.clone())?;
+        db.rollback()?;
+        assert!(db.in_transaction());
+        db.commit()?;
+
+        assert!(!db.in_transaction());
+        assert_eq!(db.get(&kept.uuid)?, &kept);
+        assert!(db.get(&dropped.uuid).is_err());
+
+        let result = db.transaction(|db| {
+            db.destroy(&kept)?;
+            db.create(dropped.clone())?;
+            db.create(Record::new("Testing".into()))?;
+            db.get("missing").map(|_| ())
+        });
+
+        assert!(matches!(result, Err(DatabaseError::ItemNotFound)));
+        assert_eq!(db.len(), 1);
+        assert_eq!(db.query_index("by_model", "Testing")?, vec![&kept]);
+
+        assert!(matches!(db.commit(), Err(DatabaseError::NoTransaction)));
+        assert!(matches!(db.rollback(), Err(DatabaseError::NoTransaction)));
+
+        Ok(())
+    }
+
+    /// Tests only committed transactions are written to the journal
+    #[test]
+    fn journaled_transactions() -> Result<(), DatabaseError> {
+        let path = PathBuf::from("transactions_test.gddb");
+        let mut db = Database::new("Transactions test", Some(path.clone()), false);
+        db.journal = true;
+        db.dump_db()?;
+
+        let traded = Record::new("Testing".into());
+        db.transaction(|db| db.create(traded.clone()))?;
+
+        db.begin();
+        db.destroy(&traded)?;
+        db.create(Record::new("Testing".into()))?;
+
+        let loaded: Database<Record> = Database::from(path.clone())?;
+        assert_eq!(loaded.items.len(), 1);
+        assert_eq!(loaded.get(&traded.uuid)?, &traded);
+
+        db.rollback()?;
+        db.compact()?;
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    /// Tests uncommitted changes can't be saved, so rolling them back leaves
+    /// the save untouched
+    #[test]
+    fn dump_in_transaction() -> Result<(), DatabaseError> {
+        let path = PathBuf::from("dump_transaction_test.gddb");
+        let mut db = Database::new("Transactions test", Some(path.clone()), false);
+        let kept = Record::new("Testing".into());
+        db.create(kept.clone())?;
+        db.dump_db()?;
+
+        db.begin();
+        db.destroy(&kept)?;
+        db.create(Record::new("Testing".into()))?;
+        assert!(matches!(db.dump_db(), Err(DatabaseError::TransactionOpen)));
+        assert!(matches!(
+            db.export_db("dump_transaction_test.json", Format::Json),
+            Err(DatabaseError::TransactionOpen)
+        ));
+        db.rollback()?;
+
+        let loaded: Database<Record> = Database::from(path.clone())?;
+        assert_eq!(loaded.items, db.items);
+        assert_eq!(loaded.get(&kept.uuid)?, &kept);
+
+        db.dump_db()?;
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+//! Unique constraints, stopping two items from sharing a value such as the
+//! `username` of a `Player`.
+//!
+//! Unlike [Database::strict_dupes], which only rejects identical items, a
+//! constraint rejects any item with the same value as another item, failing
+//! [Database::create], [Database::upsert], [Database::update],
+//! [Database::undo] and [Database::redo] with
+//! [DatabaseError::UniqueViolation]. Items whose value is [Value::Null],
+//! usually because it's missing, are never in conflict. Values are compared
+//! with [Value::total_cmp].
+//!
+//! ```rust
+//! use gddb::{Database, DatabaseError, Record};
+//!
+//! fn main() {
+//!     let mut db: Database<Record> = Database::new("GAME", None, false);
+//!     db.create_unique_path("unique_username", "username", Some("Player")).unwrap();
+//!
+//!     let mut player = Record::new("Player".into());
+//!     player.set("username", "hero");
+//!     db.create(player).unwrap();
+//!
+//!     let mut impostor = Record::new("Player".into());
+//!     impostor.set("username", "hero");
+//!     assert!(matches!(db.create(impostor), Err(DatabaseError::UniqueViolation(_))));
+//! }
+//! ```
+
+use crate::prelude::*;
+use crate::query::resolve;
+use std::sync::Arc;
+
+impl<Record: Keyed + Clone + PartialEq + Serialize + DeserializeOwned> Database<Record> {
+    /// Declares a named unique constraint over the [Value] returned by
+    /// `extract`, replacing any constraint with the same name. See
+    /// [crate::unique].
+    ///
+    /// Like indexes, constraints must be declared again after loading.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::UniqueViolation] without declaring the
+    /// constraint if items already in the database break it.
+    pub fn create_unique(
+        &mut self,
+        name: impl Into<String>,
+        extract: impl Fn(&Record) -> Value + Send + Sync + 'static,
+    ) -> Result<(), DatabaseError> {
+        let name = name.into();
+        let mut index = OrderedIndex::new(Arc::new(extract));
+
+        for item in self.items.values() {
+            index.insert(item);
+        }
+
+        if self
+            .items
+            .values()
+            .any(|item| index.same_value(item).len() > 1)
+        {
+            return Err(DatabaseError::UniqueViolation(name));
+        }
+
+        self.unique_constraints.insert(name, index);
+        Ok(())
+    }
+
+    /// Removes a unique constraint declared with [Database::create_unique].
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::IndexNotFound] if no constraint has the
+    /// given name.
+    pub fn drop_unique(&mut self, name: &str) -> Result<(), DatabaseError> {
+        self.unique_constraints
+            .remove(name)
+            .map(|_| ())
+            .ok_or(DatabaseError::IndexNotFound)
+    }
+
+    /// Checks an item doesn't share a value with another item under any
+    /// constraint, ignoring the items with the keys in `replacing`.
+    pub(crate) fn check_unique(
+        &self,
+        item: &Record,
+        replacing: &[&Record::Key],
+    ) -> Result<(), DatabaseError> {
+        for (name, index) in self.unique_constraints.iter() {
+            let conflict = index
+                .same_value(item)
+                .into_iter()
+                .any(|key| !replacing.contains(&key));
+
+            if conflict {
+                return Err(DatabaseError::UniqueViolation(name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Database<Record> {
+    /// Declares a named unique constraint over the value at a path, which is
+    /// resolved the same way as paths in a [Query]. If a model is given, only
+    /// records of that model are constrained.
+    ///
+    /// See [Database::create_unique] for the errors this can return.
+    pub fn create_unique_path(
+        &mut self,
+        name: impl Into<String>,
+        path: impl Into<String>,
+        model: Option<&str>,
+    ) -> Result<(), DatabaseError> {
+        let path = path.into();
+        let model = model.map(String::from);
+
+        self.create_unique(name, move |record: &Record| {
+            if model.as_ref().is_some_and(|model| &record.model != model) {
+                return Value::Null;
+            }
+
+            resolve(record, &path).map_or(Value::Null, |value| value.into_owned())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests constraints are enforced by every change, scoped by model and
+    /// checked against existing records when declared
+    #[test]
+    fn unique_constraints() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Unique test", None, false);
+        let player = |name: &str| {
+            let mut player = Record::new("Player".into());
+            player.set("username", name);
+            player
+        };
+
+        let hero = player("hero");
+        db.create(hero.clone())?;
+        db.create(player("hero"))?;
+        assert!(matches!(
+            db.create_unique_path("username", "username", Some("Player")),
+            Err(DatabaseError::UniqueViolation(_))
+        ));
+
+        db.destroy_where(|record| record.uuid != hero.uuid)?;
+        db.create_unique_path("username", "username", Some("Player"))?;
+
+        let rival = player("rival");
+        db.create(rival.clone())?;
+        assert!(matches!(
+            db.create(player("hero")),
+            Err(DatabaseError::UniqueViolation(name)) if name == "username"
+        ));
+        assert!(db
+            .modify(&rival.uuid, |r| r.set("username", "hero"))
+            .is_err());
+        assert!(db.upsert(player("rival")).is_err());
+
+        db.modify(&hero.uuid, |r| r.set("level", 2))?;
+        db.modify(&hero.uuid, |r| r.set("username", "legend"))?;
+        db.create(player("hero"))?;
+        db.create(Record::new("Player".into()))?;
+        db.create(Record::new("Player".into()))?;
+
+        let mut pet = Record::new("Pet".into());
+        pet.set("username", "rival");
+        db.create(pet)?;
+
+        db.drop_unique("username")?;
+        db.create(player("rival"))?;
+        assert!(matches!(
+            db.drop_unique("username"),
+            Err(DatabaseError::IndexNotFound)
+        ));
+
+        Ok(())
+    }
+
+    /// Tests undo doesn't restore items breaking a constraint, and that
+    /// different maps and `NaN` values don't conflict
+    #[test]
+    fn unique_history() -> Result<(), DatabaseError> {
+        let mut db: Database<Record> = Database::new("Unique history test", None, false);
+        db.history_depth = 10;
+        db.create_unique_path("username", "username", None)?;
+        db.create_unique_path("stats", "stats", None)?;
+
+        let mut hero = Record::new("Player".into());
+        hero.set("username", "hero");
+        db.create(hero.clone())?;
+        db.destroy(&hero)?;
+
+        db.history_depth = 0;
+        let mut impostor = Record::new("Player".into());
+        impostor.set("username", "hero");
+        db.create(impostor.clone())?;
+        db.history_depth = 10;
+
+        assert!(matches!(db.undo(), Err(DatabaseError::UniqueViolation(_))));
+        assert_eq!(db.len(), 1);
+        assert!(db.can_undo());
+
+        let mut created = Vec::new();
+
+        for hp in [1.0, 2.0, f64::NAN, 1.0] {
+            let mut stats = Value::map();
+            stats.insert("hp", hp);
+
+            let mut player = Record::new("Player".into());
+            player.set("stats", stats);
+            created.push(db.create(player).is_ok());
+        }
+        assert_eq!(created, [true, true, true, false]);
+
+        Ok(())
+    }
+}
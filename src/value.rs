@@ -0,0 +1,745 @@
+//! Contains the structured [Value] type used for [crate::Record] attributes.
+
+use crate::prelude::*;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserializer, Serializer};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A structured, serializable value mirroring the data a Godot dictionary can
+/// hold: maps, arrays, integers, floats, strings, booleans and null.
+///
+/// Maps are ordered by key so two equal values always serialize identically.
+///
+/// Human-readable formats such as JSON serialize values naturally, e.g.
+/// `{"hp": 10}`, while binary formats keep the variant so integers and floats
+/// survive a round trip.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Value {
+    /// The absence of a value, `null` in GDScript.
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Creates a new, empty [Value::Map].
+    pub fn map() -> Self {
+        Value::Map(BTreeMap::new())
+    }
+
+    /// Gets a direct child of this value, either by key for a [Value::Map] or
+    /// by index for a [Value::Array].
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(map) => map.get(key),
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+
+    /// Mutably gets a direct child of this value, see [Value::get].
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match self {
+            Value::Map(map) => map.get_mut(key),
+            Value::Array(items) => items.get_mut(key.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+
+    /// Gets a nested value from a `.` separated path such as
+    /// `inventory.0.id`, where numeric parts index into arrays.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::Value;
+    ///
+    /// fn main() {
+    ///     let mut item = Value::map();
+    ///     item.insert("id", "sword");
+    ///
+    ///     let mut player = Value::map();
+    ///     player.insert("inventory", vec![item]);
+    ///
+    ///     assert_eq!(player.get_path("inventory.0.id"), Some(&Value::from("sword")));
+    /// }
+    /// ```
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        path.split('.').try_fold(self, |value, key| value.get(key))
+    }
+
+    /// Mutably gets a nested value from a `.` separated path, see
+    /// [Value::get_path].
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        path.split('.')
+            .try_fold(self, |value, key| value.get_mut(key))
+    }
+
+    /// Inserts a key into this value, turning it into a [Value::Map] first if
+    /// it is anything else. Returns the value previously at the key.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<Value>) -> Option<Value> {
+        if !matches!(self, Value::Map(_)) {
+            *self = Value::map();
+        }
+
+        match self {
+            Value::Map(map) => map.insert(key.into(), value.into()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Sets a nested value at a `.` separated path, see [Value::get_path].
+    /// Missing or null parents are created as maps, and an index one past the
+    /// end of an array appends to it. Returns the value previously at the
+    /// path.
+    ///
+    /// # Errors
+    ///
+    /// Will return [DatabaseError::WrongType] without changing anything if a
+    /// parent is neither a map nor an array, or an array index is past its
+    /// end.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::Value;
+    ///
+    /// fn main() {
+    ///     let mut player = Value::map();
+    ///     player.set_path("stats.hp", 10).unwrap();
+    ///
+    ///     assert_eq!(player.get_path("stats.hp"), Some(&Value::Int(10)));
+    ///     assert!(player.set_path("stats.hp.max", 20).is_err());
+    /// }
+    /// ```
+    pub fn set_path(
+        &mut self,
+        path: &str,
+        value: impl Into<Value>,
+    ) -> Result<Option<Value>, DatabaseError> {
+        let mut target = self;
+
+        for part in path.split('.') {
+            if target.is_null() {
+                *target = Value::map();
+            }
+
+            target = target
+                .entry(part)
+                .ok_or_else(|| DatabaseError::WrongType(path.into()))?;
+        }
+
+        let previous = std::mem::replace(target, value.into());
+        Ok(Some(previous).filter(|previous| !previous.is_null()))
+    }
+
+    /// Mutably gets a direct child of this value like [Value::get_mut],
+    /// adding it as [Value::Null] if it's missing from a map or one past the
+    /// end of an array.
+    fn entry(&mut self, key: &str) -> Option<&mut Value> {
+        match self {
+            Value::Map(map) => Some(map.entry(key.to_string()).or_insert(Value::Null)),
+            Value::Array(items) => {
+                let index = key.parse::<usize>().ok()?;
+
+                if index == items.len() {
+                    items.push(Value::Null);
+                }
+
+                items.get_mut(index)
+            }
+            _ => None,
+        }
+    }
+
+    /// Removes a nested value from a `.` separated path, see
+    /// [Value::get_path]. Returns the removed value, if there was one.
+    pub fn remove_path(&mut self, path: &str) -> Option<Value> {
+        let (parent, key) = match path.rsplit_once('.') {
+            Some((parent, key)) => (self.get_path_mut(parent)?, key),
+            None => (self, path),
+        };
+
+        match parent {
+            Value::Map(map) => map.remove(key),
+            Value::Array(items) => {
+                let index = key.parse::<usize>().ok()?;
+                (index < items.len()).then(|| items.remove(index))
+            }
+            _ => None,
+        }
+    }
+
+    /// Deep merges another value into this one. Keys of maps are merged
+    /// recursively, while anything else in `other` replaces what it's merged
+    /// into.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use gddb::Value;
+    ///
+    /// fn main() {
+    ///     let mut stats = Value::map();
+    ///     stats.insert("hp", 10);
+    ///     stats.insert("mp", 5);
+    ///
+    ///     let mut player = Value::map();
+    ///     player.insert("stats", stats);
+    ///
+    ///     let mut healed = Value::map();
+    ///     healed.insert("hp", 20);
+    ///
+    ///     let mut patch = Value::map();
+    ///     patch.insert("stats", healed);
+    ///
+    ///     player.merge(patch);
+    ///     assert_eq!(player.get_path("stats.hp"), Some(&Value::Int(20)));
+    ///     assert_eq!(player.get_path("stats.mp"), Some(&Value::Int(5)));
+    /// }
+    /// ```
+    pub fn merge(&mut self, other: Value) {
+        match (self, other) {
+            (Value::Map(map), Value::Map(other)) => {
+                for (key, value) in other {
+                    match map.get_mut(&key) {
+                        Some(existing) => existing.merge(value),
+                        None => {
+                            map.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (this, other) => *this = other,
+        }
+    }
+
+    /// Orders any two values, sorting `null` first followed by booleans,
+    /// numbers, strings, arrays and maps. Integers and floats compare exactly
+    /// by their numeric value, with `NaN` sorted after every other number as
+    /// [f64::total_cmp] does. Arrays compare element by element, and maps
+    /// compare entry by entry in the order of their keys.
+    pub fn total_cmp(&self, other: &Value) -> Ordering {
+        fn rank(value: &Value) -> u8 {
+            match value {
+                Value::Null => 0,
+                Value::Bool(_) => 1,
+                Value::Int(_) | Value::Float(_) => 2,
+                Value::String(_) => 3,
+                Value::Array(_) => 4,
+                Value::Map(_) => 5,
+            }
+        }
+
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Float(a), Value::Float(b)) if a == b => Ordering::Equal,
+            (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+            (Value::Int(a), Value::Float(b)) => cmp_int_float(*a, *b),
+            (Value::Float(a), Value::Int(b)) => cmp_int_float(*b, *a).reverse(),
+            (Value::Array(a), Value::Array(b)) => a
+                .iter()
+                .zip(b)
+                .map(|(a, b)| a.total_cmp(b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Value::Map(a), Value::Map(b)) => a
+                .iter()
+                .zip(b)
+                .map(|((a_key, a), (b_key, b))| a_key.cmp(b_key).then_with(|| a.total_cmp(b)))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
+
+    /// Returns `true` if this is [Value::Null].
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Returns the inner boolean of a [Value::Bool].
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner integer of a [Value::Int].
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns the number held by a [Value::Float] or [Value::Int].
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(f) => Some(*f),
+            Value::Int(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner string of a [Value::String].
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner items of a [Value::Array].
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner map of a [Value::Map].
+    pub fn as_map(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the value as JSON.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", serde_json::Value::from(self.clone()))
+    }
+}
+
+/// Mirrors [Value] for binary formats, which can't infer the variant.
+#[derive(Serialize)]
+#[serde(rename = "Value")]
+enum TaggedRef<'a> {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(&'a str),
+    Array(&'a [Value]),
+    Map(&'a BTreeMap<String, Value>),
+}
+
+/// Owned version of [TaggedRef] for deserializing.
+#[derive(Deserialize)]
+#[serde(rename = "Value")]
+enum Tagged {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            let tagged = match self {
+                Value::Null => TaggedRef::Null,
+                Value::Bool(b) => TaggedRef::Bool(*b),
+                Value::Int(i) => TaggedRef::Int(*i),
+                Value::Float(f) => TaggedRef::Float(*f),
+                Value::String(s) => TaggedRef::String(s),
+                Value::Array(items) => TaggedRef::Array(items),
+                Value::Map(map) => TaggedRef::Map(map),
+            };
+
+            return tagged.serialize(serializer);
+        }
+
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Int(i) => serializer.serialize_i64(*i),
+            Value::Float(f) => serializer.serialize_f64(*f),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Array(items) => serializer.collect_seq(items),
+            Value::Map(map) => serializer.collect_map(map),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            return Ok(match Tagged::deserialize(deserializer)? {
+                Tagged::Null => Value::Null,
+                Tagged::Bool(b) => Value::Bool(b),
+                Tagged::Int(i) => Value::Int(i),
+                Tagged::Float(f) => Value::Float(f),
+                Tagged::String(s) => Value::String(s),
+                Tagged::Array(items) => Value::Array(items),
+                Tagged::Map(map) => Value::Map(map),
+            });
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// Deserializes a [Value] from any self-describing format.
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, i: i64) -> Result<Value, E> {
+        Ok(Value::Int(i))
+    }
+
+    fn visit_u64<E: de::Error>(self, u: u64) -> Result<Value, E> {
+        Ok(i64::try_from(u).map_or(Value::Float(u as f64), Value::Int))
+    }
+
+    fn visit_f64<E: de::Error>(self, f: f64) -> Result<Value, E> {
+        Ok(Value::Float(f))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.into()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = vec![];
+
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        let mut map = BTreeMap::new();
+
+        while let Some((key, value)) = access.next_entry()? {
+            map.insert(key, value);
+        }
+
+        Ok(Value::Map(map))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Self {
+        Value::Int(i.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Int(i)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Value::Float(f)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+/// Compares an integer with a float exactly, without rounding the integer to
+/// a float first. `NaN` is ordered like [f64::total_cmp], after every number
+/// unless its sign is negative.
+fn cmp_int_float(int: i64, float: f64) -> Ordering {
+    if float.is_nan() {
+        return if float.is_sign_negative() {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        };
+    }
+
+    // 2^63 is exactly representable, unlike `i64::MAX`
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+    if float >= LIMIT {
+        return Ordering::Less;
+    }
+
+    if float < -LIMIT {
+        return Ordering::Greater;
+    }
+
+    let whole = float.trunc();
+
+    int.cmp(&(whole as i64))
+        .then_with(|| whole.partial_cmp(&float).unwrap_or(Ordering::Equal))
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(map: BTreeMap<String, Value>) -> Self {
+        Value::Map(map)
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::Int(i),
+                None => Value::Float(n.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(items) => {
+                Value::Array(items.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(map) => {
+                Value::Map(map.into_iter().map(|(k, v)| (k, Value::from(v))).collect())
+            }
+        }
+    }
+}
+
+impl From<Value> for serde_json::Value {
+    /// Non-finite floats have no JSON representation and become `null`.
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(b),
+            Value::Int(i) => serde_json::Value::from(i),
+            Value::Float(f) => serde_json::Number::from_f64(f)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            Value::String(s) => serde_json::Value::String(s),
+            Value::Array(items) => {
+                serde_json::Value::Array(items.into_iter().map(serde_json::Value::from).collect())
+            }
+            Value::Map(map) => serde_json::Value::Object(
+                map.into_iter()
+                    .map(|(k, v)| (k, serde_json::Value::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests [Value::get_path] through maps and arrays
+    #[test]
+    fn path_access() {
+        let mut item = Value::map();
+        item.insert("id", "sword");
+
+        let mut player = Value::map();
+        player.insert("hp", 10);
+        player.insert("inventory", vec![item]);
+
+        assert_eq!(player.get_path("hp"), Some(&Value::Int(10)));
+        assert_eq!(
+            player.get_path("inventory.0.id"),
+            Some(&Value::from("sword"))
+        );
+        assert_eq!(player.get_path("inventory.1.id"), None);
+        assert_eq!(player.get_path("hp.max"), None);
+
+        *player.get_path_mut("inventory.0.id").unwrap() = "shield".into();
+        assert_eq!(
+            player.get_path("inventory.0.id"),
+            Some(&Value::from("shield"))
+        );
+    }
+
+    /// Tests [Value::merge], [Value::set_path] and [Value::remove_path] on
+    /// nested values
+    #[test]
+    fn merge_and_remove() {
+        let mut player = Value::map();
+        player.insert("name", "Hero");
+        player.insert("inventory", vec!["sword", "shield"]);
+
+        let mut patch = Value::map();
+        patch.insert("name", "Knight");
+        patch.insert("inventory", vec!["axe"]);
+        patch.insert("stats", Value::map());
+        player.merge(patch);
+
+        assert_eq!(player.get_path("name"), Some(&Value::from("Knight")));
+        assert_eq!(
+            player.get_path("inventory"),
+            Some(&Value::from(vec!["axe"]))
+        );
+        assert_eq!(player.get_path("stats"), Some(&Value::map()));
+
+        let before = player.clone();
+        assert!(player.set_path("name.first", "Sir").is_err());
+        assert!(player.set_path("inventory.2", "bow").is_err());
+        assert!(player.set_path("inventory.first", "bow").is_err());
+        assert_eq!(player, before);
+
+        assert_eq!(player.set_path("stats.hp", 10).unwrap(), None);
+        assert_eq!(
+            player.set_path("inventory.0", "bow").unwrap(),
+            Some(Value::from("axe"))
+        );
+        player.set_path("inventory.1", "arrow").unwrap();
+        assert_eq!(player.get_path("stats.hp"), Some(&Value::Int(10)));
+        assert_eq!(player.get_path("inventory.0"), Some(&Value::from("bow")));
+        assert_eq!(player.get_path("inventory.1"), Some(&Value::from("arrow")));
+
+        assert_eq!(player.remove_path("inventory.0"), Some(Value::from("bow")));
+        assert_eq!(
+            player.remove_path("inventory.0"),
+            Some(Value::from("arrow"))
+        );
+        assert_eq!(player.remove_path("inventory.0"), None);
+        assert_eq!(player.remove_path("name.last"), None);
+        assert!(player.remove_path("stats").is_some());
+        assert_eq!(player.get_path("stats"), None);
+    }
+
+    /// Tests values serialize naturally as JSON and keep their variant in
+    /// bincode
+    #[test]
+    fn serde_formats() {
+        let mut value = Value::map();
+        value.insert("hp", 10);
+        value.insert("speed", 1.0);
+        value.insert("tags", vec!["a"]);
+        value.insert("boss", Value::Null);
+
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"boss":null,"hp":10,"speed":1.0,"tags":["a"]}"#);
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+
+        let binary = bincode::serialize(&value).unwrap();
+        assert_eq!(bincode::deserialize::<Value>(&binary).unwrap(), value);
+    }
+
+    /// Tests [Value::total_cmp] is a consistent order over every kind of
+    /// value, including `NaN`, large integers and maps
+    #[test]
+    fn total_order() {
+        let big = 1i64 << 53;
+        let map = |hp: i64| {
+            let mut map = Value::map();
+            map.insert("hp", hp);
+            map
+        };
+
+        let sorted = [
+            Value::Null,
+            Value::Bool(false),
+            Value::Float(f64::NEG_INFINITY),
+            Value::Int(-3),
+            Value::Float(-2.5),
+            Value::Int(big),
+            Value::Int(big + 1),
+            Value::Float(f64::INFINITY),
+            Value::Float(f64::NAN),
+            Value::from("a"),
+            Value::from(vec![1]),
+            Value::map(),
+            map(1),
+            map(2),
+        ];
+
+        for (i, a) in sorted.iter().enumerate() {
+            for (j, b) in sorted.iter().enumerate() {
+                assert_eq!(a.total_cmp(b), i.cmp(&j), "{:?} against {:?}", a, b);
+            }
+        }
+
+        assert_eq!(
+            Value::Int(big).total_cmp(&Value::Float(big as f64)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::Float(-0.0).total_cmp(&Value::Float(0.0)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::Float(f64::NAN).total_cmp(&Value::Float(f64::NAN)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::Int(i64::MAX).total_cmp(&Value::Float(1e19)),
+            Ordering::Less
+        );
+    }
+
+    /// Tests conversion to and from JSON is lossless
+    #[test]
+    fn json_round_trip() {
+        let json: serde_json::Value =
+            serde_json::from_str(r#"{"a":[1,2.5,"x",true,null],"b":{"c":-3}}"#).unwrap();
+
+        let value = Value::from(json.clone());
+        assert_eq!(value.get_path("a.1"), Some(&Value::Float(2.5)));
+        assert_eq!(value.get_path("b.c"), Some(&Value::Int(-3)));
+
+        assert_eq!(serde_json::Value::from(value), json);
+    }
+}